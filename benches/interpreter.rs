@@ -0,0 +1,139 @@
+// MIT License
+//
+// Copyright (c) 2023 Ramesh Poudel
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+/*
+* Times arithmetic-heavy bish loops. Run with `cargo bench`; each program is
+* compiled once and run several times, and the fastest run is reported.
+* It then times the same stack traffic on Value and on WideValue, the
+* 24-byte layout Value had before strings became an Rc<String>.
+*
+*   cargo bench -- sum        only programs whose name contains "sum"
+*   cargo bench -- stack      only the Value/WideValue comparison
+*/
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::mem;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use bish::diagnostics::LintLevel;
+use bish::vm::Native;
+use bish::{Value, Vm};
+
+const RUNS: usize = 5;
+const STACK_ROUNDS: i64 = 10_000_000;
+
+const PROGRAMS: [(&str, &str); 4] = [
+    ("int_sum", "rakha i ma 0; rakha total ma 0; ghumau i sano 1000000 { total ma total + i * 2 - 1; i ma i + 1; }"),
+    ("float_sum", "rakha i ma 0; rakha x ma 0.0; ghumau i sano 1000000 { x ma x * 0.5 + 1.5; i ma i + 1; }"),
+    ("branches", "rakha i ma 0; rakha even ma 0; ghumau i sano 1000000 { yadi (i / 2 * 2 barabar i) { even ma even + 1; } natra { even ma even - 1; } i ma i + 1; }"),
+    ("strings", "rakha i ma 0; rakha hit ma 0; rakha a ma \"bish\"; rakha b ma \"bish\"; ghumau i sano 1000000 { yadi (a barabar b) { hit ma hit + 1; } i ma i + 1; }")
+];
+
+/*
+* Value's variants with the fat Rc<str> string it used to hold.
+*/
+#[allow(dead_code)]
+enum WideValue {
+    Double(f64),
+    Integer(i64),
+    Boolean(bool),
+    String(Rc<str>),
+    Array(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<HashMap<Rc<str>, Value>>>),
+    Function(Rc<Native>),
+    Nil,
+    Unknown
+}
+
+/*
+* What `total ma total + i;` does to the stack: push two integers, pop
+* them, push their sum and pop it into the global.
+*/
+macro_rules! stack_rounds {
+    ($name:ident, $value:ident) => {
+        #[inline(never)]
+        fn $name(stack: &mut Vec<$value>) -> i64 {
+            let mut total: i64 = 0;
+            for i in 0..STACK_ROUNDS {
+                stack.push($value::Integer(total));
+                stack.push($value::Integer(i));
+                if let (Some($value::Integer(b)), Some($value::Integer(a))) = (stack.pop(), stack.pop()) {
+                    stack.push($value::Integer(a + b));
+                }
+                if let Some($value::Integer(sum)) = stack.pop() {
+                    total = sum;
+                }
+            }
+            total
+        }
+    };
+}
+
+stack_rounds!(value_rounds, Value);
+stack_rounds!(wide_rounds, WideValue);
+
+fn best_of<T>(mut round: impl FnMut() -> T) -> Duration {
+    let mut best: Duration = Duration::MAX;
+    for _ in 0..RUNS {
+        let started: Instant = Instant::now();
+        black_box(round());
+        best = best.min(started.elapsed());
+    }
+    best
+}
+
+fn main() {
+    let filter: Option<String> = std::env::args().skip(1).find(|arg| !arg.starts_with('-'));
+    println!("size_of::<Value>() = {} bytes", mem::size_of::<Value>());
+    for (name, code) in PROGRAMS {
+        if filter.as_ref().is_some_and(|filter| !name.contains(filter.as_str())) {
+            continue;
+        }
+        let mut vm: Vm = Vm::new();
+        vm.lints.set_all(LintLevel::Allow);
+        vm.compile(name, code.to_owned()).unwrap();
+        let mut best: Duration = Duration::MAX;
+        for _ in 0..RUNS {
+            vm.reset();
+            let started: Instant = Instant::now();
+            vm.run().unwrap();
+            best = best.min(started.elapsed());
+        }
+        println!("{:<10} {:>8.2} ms  {:>6.2} ns/instruction", name, best.as_secs_f64() * 1e3, best.as_nanos() as f64 / vm.executed() as f64);
+    }
+    if filter.is_some_and(|filter| !"stack".contains(filter.as_str())) {
+        return;
+    }
+
+    let mut values: Vec<Value> = Vec::new();
+    let mut wide: Vec<WideValue> = Vec::new();
+    for (name, size, best) in [
+        ("stack", mem::size_of::<Value>(), best_of(|| value_rounds(black_box(&mut values)))),
+        ("stack_wide", mem::size_of::<WideValue>(), best_of(|| wide_rounds(black_box(&mut wide))))
+    ] {
+        println!("{:<10} {:>8.2} ms  {:>6.2} ns/round ({} byte values)", name, best.as_secs_f64() * 1e3, best.as_nanos() as f64 / STACK_ROUNDS as f64, size);
+    }
+}
@@ -0,0 +1,182 @@
+// MIT License
+//
+// Copyright (c) 2023 Ramesh Poudel
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+#![allow(dead_code)]
+
+use crate::scanner;
+
+/*
+* Where a node came from in the source. Line and column are copied from the
+* token the node starts at.
+*/
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize
+}
+
+impl Span {
+    pub fn of(token: &scanner::Token) -> Span {
+        Span {
+            line: token.line,
+            column: token.column
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Literal {
+    Integer(i64),
+    Double(f64),
+    Boolean(bool),
+    CString(String),
+    Nil
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UnaryOp {
+    Negate,     // -
+    Not         // chhaina
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BinaryOp {
+    Add,        // +
+    Subtract,   // -
+    Multiply,   // *
+    Divide,     // /
+    Greater,    // thulo
+    Less,       // sano
+    Equal,      // barabar
+    And,        // &
+    Or          // |
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Expr {
+    Literal {
+        value: Literal,
+        span: Span
+    },
+    Variable {
+        name: String,
+        span: Span
+    },
+    Grouping {
+        inner: Box<Expr>,
+        span: Span
+    },
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+        span: Span
+    },
+    Binary {
+        op: BinaryOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+        span: Span
+    },
+    // <callee>(<args>, ...); `span` is the callee's name.
+    Call {
+        callee: String,
+        args: Vec<Expr>,
+        span: Span
+    }
+}
+
+impl Expr {
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Literal { span, .. } |
+            Expr::Variable { span, .. } |
+            Expr::Grouping { span, .. } |
+            Expr::Unary { span, .. } |
+            Expr::Binary { span, .. } |
+            Expr::Call { span, .. } => *span
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Stmt {
+    // dekhau <value>;
+    Print {
+        value: Expr,
+        span: Span
+    },
+    // <expr>;
+    Expression {
+        expr: Expr,
+        span: Span
+    },
+    // rakha <name> [ma <initializer>];
+    VarDecl {
+        name: String,
+        initializer: Option<Expr>,
+        span: Span
+    },
+    // <name> ma <value>;
+    Assign {
+        name: String,
+        value: Expr,
+        span: Span
+    },
+    // yadi <condition> { ... } [natra { ... } | natra yadi ...]
+    // `else_branch` is either a Stmt::Block or another Stmt::If.
+    If {
+        condition: Expr,
+        then_branch: Vec<Stmt>,
+        else_branch: Option<Box<Stmt>>,
+        span: Span
+    },
+    // ghumau <condition> { ... }
+    While {
+        condition: Expr,
+        body: Vec<Stmt>,
+        span: Span
+    },
+    // patak;
+    Break {
+        span: Span
+    },
+    // { ... }
+    Block {
+        body: Vec<Stmt>,
+        span: Span
+    }
+}
+
+impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Print { span, .. } |
+            Stmt::Expression { span, .. } |
+            Stmt::VarDecl { span, .. } |
+            Stmt::Assign { span, .. } |
+            Stmt::If { span, .. } |
+            Stmt::While { span, .. } |
+            Stmt::Break { span } |
+            Stmt::Block { span, .. } => *span
+        }
+    }
+}
@@ -0,0 +1,232 @@
+// MIT License
+//
+// Copyright (c) 2023 Ramesh Poudel
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+#![allow(dead_code)]
+
+use crate::chunk::{Chunk, PoolItem, Value};
+
+/*
+* Layout of a .bishc file. All integers are big-endian, like the operands
+* in Chunk.code.
+*
+*   magic       4 bytes   "BSHC"
+*   version     u16       FORMAT_VERSION
+*   constants   u32 count, then per constant a tag byte and its payload:
+*                 TAG_INT     i64
+*                 TAG_DOUBLE  f64 bits
+*                 TAG_BOOL    u8 (0 or 1)
+*                 TAG_STRING  u32 byte length, UTF-8 bytes
+*                 TAG_NIL     nothing
+*   code        u32 length, bytes
+*   lines       u32 run count, then (line u32, run length u32) pairs which
+*               together cover every byte of code
+*   checksum    u32 FNV-1a of everything before it
+*/
+pub const MAGIC: [u8; 4] = *b"BSHC";
+pub const FORMAT_VERSION: u16 = 1;
+pub const EXTENSION: &str = "bishc";
+
+const TAG_INT: u8 = 0;
+const TAG_DOUBLE: u8 = 1;
+const TAG_BOOL: u8 = 2;
+const TAG_STRING: u8 = 3;
+const TAG_NIL: u8 = 4;
+
+#[derive(Debug)]
+pub enum LoadError {
+    Corrupt(String),
+    UnsupportedVersion(u16)
+}
+
+/*
+* Arrays, maps and functions only exist while a program runs and have no
+* tag in the format. The compiler never puts them in the constant pool,
+* but a chunk built by hand can hold them.
+*/
+#[derive(Debug)]
+pub enum SaveError {
+    // Index and type name of the constant.
+    UnsupportedConstant(usize, String)
+}
+
+pub fn save(chunk: &Chunk) -> Result<Vec<u8>, SaveError> {
+    let mut out: Vec<u8> = Vec::new();
+    out.extend_from_slice(&MAGIC);
+    out.extend_from_slice(&FORMAT_VERSION.to_be_bytes());
+
+    out.extend_from_slice(&(chunk.const_pool.data.len() as u32).to_be_bytes());
+    for (index, item) in chunk.const_pool.data.iter().enumerate() {
+        match &item.data {
+            Value::Integer(value) => {
+                out.push(TAG_INT);
+                out.extend_from_slice(&value.to_be_bytes());
+            },
+            Value::Double(value) => {
+                out.push(TAG_DOUBLE);
+                out.extend_from_slice(&value.to_bits().to_be_bytes());
+            },
+            Value::Boolean(value) => {
+                out.push(TAG_BOOL);
+                out.push(*value as u8);
+            },
+            Value::String(value) => {
+                out.push(TAG_STRING);
+                out.extend_from_slice(&(value.len() as u32).to_be_bytes());
+                out.extend_from_slice(value.as_bytes());
+            },
+            Value::Nil => out.push(TAG_NIL),
+            other @ (Value::Unknown | Value::Array(_) | Value::Map(_) | Value::Function(_)) => {
+                return Err(SaveError::UnsupportedConstant(index, Value::name(other)));
+            }
+        }
+    }
+
+    out.extend_from_slice(&(chunk.code.len() as u32).to_be_bytes());
+    out.extend_from_slice(&chunk.code);
+
+    let mut runs: Vec<(usize, u32)> = Vec::new();
+    for line in &chunk.lines {
+        match runs.last_mut() {
+            Some((last, count)) if last == line => *count += 1,
+            _ => runs.push((*line, 1))
+        }
+    }
+    out.extend_from_slice(&(runs.len() as u32).to_be_bytes());
+    for (line, count) in runs {
+        out.extend_from_slice(&(line as u32).to_be_bytes());
+        out.extend_from_slice(&count.to_be_bytes());
+    }
+
+    let checksum: u32 = fnv1a(&out);
+    out.extend_from_slice(&checksum.to_be_bytes());
+    Ok(out)
+}
+
+pub fn load(bytes: &[u8]) -> Result<Chunk, LoadError> {
+    if bytes.len() < MAGIC.len() || bytes[..MAGIC.len()] != MAGIC {
+        return Err(LoadError::Corrupt(String::from("file ko suruma .bishc ko magic header chhaina")));
+    }
+    let mut reader: Reader = Reader { bytes, offset: MAGIC.len() };
+    let version: u16 = reader.u16()?;
+    if version != FORMAT_VERSION {
+        return Err(LoadError::UnsupportedVersion(version));
+    }
+
+    // The checksum is verified before anything else is decoded, so a damaged
+    // file is reported as such rather than as whatever the damage decodes to.
+    if bytes.len() < reader.offset + 4 {
+        return Err(LoadError::Corrupt(String::from("file adhuro chha")));
+    }
+    let body: &[u8] = &bytes[..bytes.len() - 4];
+    let stored: u32 = u32::from_be_bytes(bytes[bytes.len() - 4..].try_into().unwrap());
+    if fnv1a(body) != stored {
+        return Err(LoadError::Corrupt(String::from("checksum milena")));
+    }
+    let mut reader: Reader = Reader { bytes: body, offset: reader.offset };
+
+    let mut chunk: Chunk = Chunk::new();
+    let const_count: u32 = reader.u32()?;
+    for index in 0..const_count as usize {
+        let value: Value = match reader.u8()? {
+            TAG_INT => Value::Integer(i64::from_be_bytes(reader.array()?)),
+            TAG_DOUBLE => Value::Double(f64::from_bits(u64::from_be_bytes(reader.array()?))),
+            TAG_BOOL => match reader.u8()? {
+                0 => Value::Boolean(false),
+                1 => Value::Boolean(true),
+                other => return Err(LoadError::Corrupt(format!("constant {} ma bool ko value {} milena", index, other)))
+            },
+            TAG_STRING => {
+                let len: usize = reader.u32()? as usize;
+                let value: String = String::from_utf8(reader.take(len)?.to_vec())
+                    .map_err(|_| LoadError::Corrupt(format!("constant {} ko string UTF-8 chhaina", index)))?;
+                Value::from(value)
+            },
+            TAG_NIL => Value::Nil,
+            tag => return Err(LoadError::Corrupt(format!("constant {} ko tag {} thaha chhaina", index, tag)))
+        };
+        chunk.const_pool.data.push_back(PoolItem { data: value, index });
+        chunk.const_pool.size += 1;
+    }
+
+    let code_len: usize = reader.u32()? as usize;
+    chunk.code = reader.take(code_len)?.to_vec();
+    chunk.size = chunk.code.len();
+
+    let run_count: u32 = reader.u32()?;
+    for _ in 0..run_count {
+        let line: usize = reader.u32()? as usize;
+        let count: usize = reader.u32()? as usize;
+        if chunk.lines.len() + count > code_len {
+            return Err(LoadError::Corrupt(String::from("line table code bhanda lamo chha")));
+        }
+        chunk.lines.resize(chunk.lines.len() + count, line);
+    }
+    if chunk.lines.len() != code_len {
+        return Err(LoadError::Corrupt(String::from("line table le sabai code sametena")));
+    }
+    if reader.offset != body.len() {
+        return Err(LoadError::Corrupt(String::from("file ko antyama thap bytes chhan")));
+    }
+    Ok(chunk)
+}
+
+struct Reader<'reading> {
+    bytes: &'reading [u8],
+    offset: usize
+}
+
+impl<'reading> Reader<'reading> {
+    fn take(&mut self, len: usize) -> Result<&'reading [u8], LoadError> {
+        if self.bytes.len() - self.offset < len {
+            return Err(LoadError::Corrupt(String::from("file adhuro chha")));
+        }
+        let slice: &[u8] = &self.bytes[self.offset..self.offset + len];
+        self.offset += len;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], LoadError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, LoadError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, LoadError> {
+        Ok(u16::from_be_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, LoadError> {
+        Ok(u32::from_be_bytes(self.array()?))
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u32 {
+    let mut hash: u32 = 0x811c9dc5;
+    for byte in bytes {
+        hash ^= *byte as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    hash
+}
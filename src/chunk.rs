@@ -25,9 +25,14 @@
 #![allow(non_camel_case_types)]
 #![allow(clippy::new_without_default)]
 
-use std::collections::VecDeque;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::rc::Rc;
 
-#[derive(Debug, PartialEq, Eq)]
+use crate::vm::Native;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum OpCode {
     OP_NOP = 100,
     OP_RETURN = 0,
@@ -54,13 +59,24 @@ pub enum OpCode {
     OP_DEF_GLOBAL = 21,
     OP_LOAD_GLOBAL = 22,
     OP_JMP_IF_FALSE = 23,
+    // Unconditional forward jump; skips 'natra' blocks and leaves loops on 'patak'.
     OP_ELSE = 24,
+    OP_SET_GLOBAL = 25,
+    // Unconditional backward jump to the top of a 'ghumau' loop.
+    OP_LOOP = 26,
+    // Calls a native function: u16 constant index of its name, then a u8
+    // argument count. The arguments are on the stack, last one on top.
+    OP_CALL = 27,
     OP_COUNT
 }
 
 impl OpCode {
-    pub fn from_u8(c: u8) -> OpCode {
-        match c {
+    /*
+    * None for bytes that are not an opcode, so loaded or hand-built code
+    * with garbage in it is caught instead of running as OP_NOP.
+    */
+    pub fn from_u8(c: u8) -> Option<OpCode> {
+        let opcode: OpCode = match c {
             0 => OpCode::OP_RETURN,
             1 => OpCode::OP_CONST,
             2 => OpCode::OP_AND,
@@ -86,7 +102,29 @@ impl OpCode {
             22 => OpCode::OP_LOAD_GLOBAL,
             23 => OpCode::OP_JMP_IF_FALSE,
             24 => OpCode::OP_ELSE,
-            _ => OpCode::OP_NOP
+            25 => OpCode::OP_SET_GLOBAL,
+            26 => OpCode::OP_LOOP,
+            27 => OpCode::OP_CALL,
+            100 => OpCode::OP_NOP,
+            _ => return None
+        };
+        Some(opcode)
+    }
+
+    /*
+    * Number of operand bytes that follow the opcode in `Chunk.code`.
+    */
+    pub fn operand_len(&self) -> usize {
+        match self {
+            OpCode::OP_CONST |
+            OpCode::OP_DEF_GLOBAL |
+            OpCode::OP_LOAD_GLOBAL |
+            OpCode::OP_SET_GLOBAL |
+            OpCode::OP_JMP_IF_FALSE |
+            OpCode::OP_ELSE |
+            OpCode::OP_LOOP => 2,
+            OpCode::OP_CALL => 3,
+            _ => 0
         }
     }
 }
@@ -95,6 +133,11 @@ pub struct Chunk {
     pub code: Vec<u8>,
     pub size: usize,
     pub const_pool: Pool,
+    // Source line of every byte in `code`, operands included.
+    pub lines: Vec<usize>,
+    // Where each string written with write_const sits in the pool, so that
+    // a name used many times is stored once.
+    strings: HashMap<Rc<String>, u16>
 }
 
 impl Chunk {
@@ -102,118 +145,367 @@ impl Chunk {
         Chunk {
             code: Vec::new(),
             size: 0,
-            const_pool: Pool::new()
+            const_pool: Pool::new(),
+            lines: Vec::new(),
+            strings: HashMap::new()
         }
     }
 
     #[inline]
-    pub fn write(&mut self, byte: OpCode) {
-        self.code.push(byte as u8);
+    pub fn write(&mut self, byte: OpCode, line: usize) {
+        self.write_byte(byte as u8, line);
+    }
+
+    #[inline]
+    pub fn write_byte(&mut self, byte: u8, line: usize) {
+        self.code.push(byte);
+        self.lines.push(line);
         self.size += 1;
     }
 
+    /*
+    * Operands wider than a byte (constant indices, jump offsets) are
+    * stored big-endian.
+    */
+    #[inline]
+    pub fn write_short(&mut self, value: u16, line: usize) {
+        self.write_byte(((value >> 8) & 0xFF) as u8, line);
+        self.write_byte((value & 0xFF) as u8, line);
+    }
+
     #[inline]
-    pub fn write_const_int(&mut self, val: i64) {
-        self.write(OpCode::OP_CONST);
-        self.write_const(PrimType::Integer(val));
+    pub fn read_short(&self, offset: usize) -> u16 {
+        ((self.code[offset] as u16) << 8) | (self.code[offset + 1] as u16)
     }
 
+    /*
+    * The constant writers return false, writing nothing, once the pool is
+    * full; see write_const.
+    */
     #[inline]
-    pub fn write_cstring(&mut self, value: String) {
-        self.write(OpCode::OP_CONST);
-        self.write_const(PrimType::CString(value.len(), value));
+    pub fn write_const_int(&mut self, val: i64, line: usize) -> bool {
+        self.write_const_instr(Value::Integer(val), line)
     }
 
     #[inline]
-    pub fn write_const_double(&mut self, val: f64) {
-        self.write(OpCode::OP_CONST);
-        self.write_const(PrimType::Double(val));
+    pub fn write_cstring(&mut self, value: String, line: usize) -> bool {
+        self.write_const_instr(Value::from(value), line)
     }
 
     #[inline]
-    pub fn write_bool(&mut self, cond: bool) {
-        self.write(if cond { OpCode::OP_TRUE } else { OpCode::OP_FALSE });
-        self.write_const(PrimType::Boolean(cond));
+    pub fn write_const_double(&mut self, val: f64, line: usize) -> bool {
+        self.write_const_instr(Value::Double(val), line)
     }
 
     #[inline]
-    pub fn write_nil(&mut self) {
-        self.write(OpCode::OP_NIL);
-        self.write_const(PrimType::Nil);
+    pub fn write_bool(&mut self, cond: bool, line: usize) {
+        self.write(if cond { OpCode::OP_TRUE } else { OpCode::OP_FALSE }, line);
     }
 
     #[inline]
-    pub fn write_const(&mut self, prim_type: PrimType) {
+    pub fn write_nil(&mut self, line: usize) {
+        self.write(OpCode::OP_NIL, line);
+    }
+
+    #[inline]
+    fn write_const_instr(&mut self, prim_type: Value, line: usize) -> bool {
+        match self.write_const(prim_type) {
+            Some(index) => {
+                self.write(OpCode::OP_CONST, line);
+                self.write_short(index, line);
+                true
+            },
+            None => false
+        }
+    }
+
+    /*
+    * Adds a value to the constant pool and returns its index, which is what
+    * OP_CONST, OP_DEF_GLOBAL, OP_LOAD_GLOBAL and OP_SET_GLOBAL take as their
+    * operand. A string already in the pool is not added again. None when
+    * the pool already holds as many constants as a u16 operand can index.
+    */
+    #[inline]
+    pub fn write_const(&mut self, prim_type: Value) -> Option<u16> {
+        if let Value::String(text) = &prim_type {
+            if let Some(index) = self.strings.get(text) {
+                return Some(*index);
+            }
+        }
+        let index: usize = self.const_pool.size;
+        if index > u16::MAX as usize {
+            return None;
+        }
+        if let Value::String(text) = &prim_type {
+            self.strings.insert(text.clone(), index as u16);
+        }
         self.const_pool.data.push_back(PoolItem {
             data: prim_type,
-            index: self.const_pool.size
+            index
         });
         self.const_pool.size += 1;
+        Some(index as u16)
     }
 
-    pub fn read_const(&mut self) -> PrimType {
-        if self.const_pool.size == 0 {}
-
-        self.const_pool.size -= 1;
-        if let Some(value) = &self.const_pool.data.pop_front() {
+    pub fn read_const(&self, index: u16) -> Value {
+        if let Some(value) = self.const_pool.data.get(index as usize) {
             value.data.clone()
         }
         else {
-            PrimType::Unknown
+            Value::Unknown
         }
     }
 
-    pub fn dump(&self) {
-        let mut pool_off: usize = 0;
-        let mut code_off: usize = 0;
-        let size: usize = self.size;
-        while code_off < size {
-            self._dump_instr(&mut code_off, &mut pool_off);
+    /*
+    * Full listing of the chunk: the constant pool, then every instruction
+    * with its offset, source line and decoded operands.
+    */
+    pub fn disassemble(&self, name: &str) -> String {
+        let mut out: String = format!("== {} ==\n-- constants --\n", name);
+        for item in &self.const_pool.data {
+            out.push_str(&format!("{:>5}  {:<7} {}\n", item.index, Value::name(&item.data), Value::literal(&item.data)));
         }
+        out.push_str("-- code --\n");
+        let mut offset: usize = 0;
+        while offset < self.code.len() {
+            let (text, next): (String, usize) = self.disassemble_instr(offset);
+            out.push_str(&text);
+            out.push('\n');
+            offset = next;
+        }
+        out
     }
 
-    fn _dump_instr(&self, code_off: &mut usize, pool_off: &mut usize) {
-        let instr: u8 = self.code[*code_off];
-        let opcode = OpCode::from_u8(instr);
-        match opcode {
-            OpCode::OP_RETURN => { self._dump_simple_instr("OP_RETURN", code_off); },
-            OpCode::OP_NOP => { self._dump_simple_instr("OP_NOP", code_off); },
-            OpCode::OP_CONST => { *code_off += 1; *pool_off += 1; },
-            _ => {
-                println!("{instr}");
-                println!("Invalid Opcode");
-                std::process::exit(1);
-            }
-        }
+    /*
+    * One instruction as a line of text (without the newline), and the
+    * offset of the instruction after it. A byte that is not an opcode is
+    * shown as such and skipped on its own.
+    */
+    pub fn disassemble_instr(&self, offset: usize) -> (String, usize) {
+        let line: String = match self.lines.get(offset) {
+            Some(line) if offset > 0 && self.lines.get(offset - 1) == Some(line) => String::from("|"),
+            Some(line) => line.to_string(),
+            None => String::from("?")
+        };
+        self._format_instr(offset, &line)
     }
 
-    fn _dump_simple_instr(&self, name: &str, code_off: &mut usize) {
-        println!("{:0>4} {}", format!("{:x}", code_off), name);
-        *code_off += 1;
+    /*
+    * Like disassemble_instr, but always shows the line number: in a trace
+    * the previous line printed is not necessarily the previous byte.
+    */
+    pub fn trace_instr(&self, offset: usize) -> String {
+        let line: String = self.lines.get(offset).map_or(String::from("?"), |line| line.to_string());
+        self._format_instr(offset, &line).0
+    }
+
+    fn _format_instr(&self, offset: usize, line: &str) -> (String, usize) {
+        let prefix: String = format!("{:04x} {:>4} ", offset, line);
+        let opcode: OpCode = match OpCode::from_u8(self.code[offset]) {
+            Some(opcode) => opcode,
+            None => return (format!("{}<{} opcode hoina>", prefix, self.code[offset]), offset + 1)
+        };
+        let next: usize = offset + 1 + opcode.operand_len();
+        if next > self.code.len() {
+            return (format!("{}{:?} <operand adhuro chha>", prefix, opcode), self.code.len());
+        }
+        let operand: String = match opcode {
+            OpCode::OP_CONST |
+            OpCode::OP_DEF_GLOBAL |
+            OpCode::OP_LOAD_GLOBAL |
+            OpCode::OP_SET_GLOBAL => {
+                let index: u16 = self.read_short(offset + 1);
+                format!("{:>4} {}", index, Value::literal(&self.read_const(index)))
+            },
+            OpCode::OP_JMP_IF_FALSE |
+            OpCode::OP_ELSE => format!("{:>4} -> {:04x}", self.read_short(offset + 1), next + self.read_short(offset + 1) as usize),
+            OpCode::OP_CALL => {
+                let index: u16 = self.read_short(offset + 1);
+                format!("{:>4} {} ({} arg)", index, Value::literal(&self.read_const(index)), self.code[offset + 3])
+            },
+            OpCode::OP_LOOP => {
+                let jump: usize = self.read_short(offset + 1) as usize;
+                match next.checked_sub(jump) {
+                    Some(target) => format!("{:>4} -> {:04x}", jump, target),
+                    None => format!("{:>4} -> <code agadi>", jump)
+                }
+            },
+            _ => String::new()
+        };
+        (format!("{}{:<17} {}", prefix, format!("{:?}", opcode), operand).trim_end().to_owned(), next)
     }
 }
 
-#[derive(Clone, Debug)]
-pub enum PrimType {
+/*
+* A value as bish code sees it, and as native functions receive and return
+* it. Strings, arrays, maps and functions live on the heap behind an Rc, so
+* pushing one onto the stack or reading it out of a global or the constant
+* pool only bumps a count instead of copying it.
+*
+* Every variant holds at most one word, so a Value is a tag and a word (16
+* bytes). That is why a string is an Rc<String> rather than an Rc<str>,
+* whose pointer carries the length too: the extra hop only costs code that
+* reads the text, while every value on the stack gets smaller.
+*/
+#[derive(Clone)]
+pub enum Value {
     Double(f64),
     Integer(i64),
     Boolean(bool),
-    CString(usize, String),
+    String(Rc<String>),
+    Array(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<HashMap<Rc<str>, Value>>>),
+    Function(Rc<Native>),
     Nil,
     Unknown
 }
 
-impl PrimType {
-    pub fn name(typ: &PrimType) -> String {
+impl Value {
+    pub fn array(values: Vec<Value>) -> Value {
+        Value::Array(Rc::new(RefCell::new(values)))
+    }
+
+    pub fn map(entries: HashMap<Rc<str>, Value>) -> Value {
+        Value::Map(Rc::new(RefCell::new(entries)))
+    }
+
+    pub fn name(typ: &Value) -> String {
         match typ {
-            PrimType::Double(_) => String::from("float"),
-            PrimType::Integer(_) => String::from("int"),
-            PrimType::Boolean(_) => String::from("bool"),
-            PrimType::CString(_, _) => String::from("string"),
-            PrimType::Nil => String::from("nil"),
+            Value::Double(_) => String::from("float"),
+            Value::Integer(_) => String::from("int"),
+            Value::Boolean(_) => String::from("bool"),
+            Value::String(_) => String::from("string"),
+            Value::Array(_) => String::from("array"),
+            Value::Map(_) => String::from("map"),
+            Value::Function(_) => String::from("function"),
+            Value::Nil => String::from("nil"),
             _ => String::from("unknown")
         }
     }
+
+    /*
+    * A value as it would be written in bish source; strings are quoted and
+    * map entries are sorted by key. An array or map inside itself is
+    * written as `[...]` or `{...}`.
+    */
+    pub fn literal(typ: &Value) -> String {
+        Value::_literal(typ, &mut Vec::new())
+    }
+
+    fn _literal(typ: &Value, enclosing: &mut Vec<*const ()>) -> String {
+        match typ {
+            Value::Array(values) => {
+                let address: *const () = Rc::as_ptr(values) as *const ();
+                if enclosing.contains(&address) {
+                    return String::from("[...]");
+                }
+                enclosing.push(address);
+                let values: Vec<String> = values.borrow().iter().map(|value| Value::_literal(value, enclosing)).collect();
+                enclosing.pop();
+                format!("[{}]", values.join(", "))
+            },
+            Value::Map(entries) => {
+                let address: *const () = Rc::as_ptr(entries) as *const ();
+                if enclosing.contains(&address) {
+                    return String::from("{...}");
+                }
+                enclosing.push(address);
+                let mut entries: Vec<String> = entries.borrow().iter().map(|(key, value)| format!("{:?}: {}", key, Value::_literal(value, enclosing))).collect();
+                enclosing.pop();
+                entries.sort();
+                format!("{{{}}}", entries.join(", "))
+            },
+            Value::Double(value) => format!("{:?}", value),
+            Value::Integer(value) => value.to_string(),
+            Value::Boolean(value) => String::from(if *value { "sahi" } else { "galat" }),
+            Value::String(value) => format!("{:?}", value),
+            Value::Function(native) => format!("<function {}>", native.name),
+            Value::Nil => String::from("nil"),
+            Value::Unknown => String::from("<unknown>")
+        }
+    }
+}
+
+/*
+* Written like literal, so that printing an array that holds itself ends.
+*/
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", Value::name(self), Value::literal(self))
+    }
+}
+
+/*
+* Values are equal when they hold the same data; two functions are only
+* equal if they are the same one. While an array or map pair is being
+* compared, meeting the same pair again counts as equal, so comparing
+* arrays that hold themselves ends instead of recursing forever.
+*/
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        Value::_eq(self, other, &mut Vec::new())
+    }
+}
+
+impl Value {
+    fn _eq(lhs: &Value, rhs: &Value, comparing: &mut Vec<(*const (), *const ())>) -> bool {
+        match (lhs, rhs) {
+            (Value::Double(a), Value::Double(b)) => a == b,
+            (Value::Integer(a), Value::Integer(b)) => a == b,
+            (Value::Boolean(a), Value::Boolean(b)) => a == b,
+            // Interned strings with the same text share an allocation.
+            (Value::String(a), Value::String(b)) => Rc::ptr_eq(a, b) || a == b,
+            (Value::Array(a), Value::Array(b)) => {
+                if Rc::ptr_eq(a, b) {
+                    return true;
+                }
+                let pair: (*const (), *const ()) = (Rc::as_ptr(a) as *const (), Rc::as_ptr(b) as *const ());
+                if comparing.contains(&pair) {
+                    return true;
+                }
+                let (a, b) = (a.borrow(), b.borrow());
+                if a.len() != b.len() {
+                    return false;
+                }
+                comparing.push(pair);
+                let equal: bool = a.iter().zip(b.iter()).all(|(a, b)| Value::_eq(a, b, comparing));
+                comparing.pop();
+                equal
+            },
+            (Value::Map(a), Value::Map(b)) => {
+                if Rc::ptr_eq(a, b) {
+                    return true;
+                }
+                let pair: (*const (), *const ()) = (Rc::as_ptr(a) as *const (), Rc::as_ptr(b) as *const ());
+                if comparing.contains(&pair) {
+                    return true;
+                }
+                let (a, b) = (a.borrow(), b.borrow());
+                if a.len() != b.len() {
+                    return false;
+                }
+                comparing.push(pair);
+                let equal: bool = a.iter().all(|(key, a)| b.get(key).is_some_and(|b| Value::_eq(a, b, comparing)));
+                comparing.pop();
+                equal
+            },
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            (Value::Nil, Value::Nil) | (Value::Unknown, Value::Unknown) => true,
+            _ => false
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(text: &str) -> Value {
+        Value::String(Rc::new(text.to_owned()))
+    }
+}
+
+impl From<String> for Value {
+    fn from(text: String) -> Value {
+        Value::String(Rc::new(text))
+    }
 }
 
 pub struct Pool {
@@ -222,7 +514,7 @@ pub struct Pool {
 }
 
 pub struct PoolItem {
-    pub data: PrimType,
+    pub data: Value,
     pub index: usize
 }
 
@@ -0,0 +1,277 @@
+// MIT License
+//
+// Copyright (c) 2023 Ramesh Poudel
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+#![allow(dead_code)]
+
+use crate::ast::{BinaryOp, Expr, Literal, Span, Stmt, UnaryOp};
+use crate::chunk::{Chunk, OpCode, Value};
+use crate::diagnostics::{self, ErrorCode};
+
+/*
+* Lowers a parsed program to bytecode. The tree has already been checked by
+* the parser; what can still go wrong is a program too big for the u16
+* operands, with more constants than an index can name or a block longer
+* than a jump can cross.
+*/
+pub struct CodeGen<'generating> {
+    chunk: &'generating mut Chunk,
+    source_file_path: &'generating str,
+    line: usize,
+    // The statement or expression being generated, for errors.
+    span: Span,
+    // How many blocks deep the statement being generated is.
+    depth: usize,
+    loop_breaks: Vec<Vec<(usize, Span)>>,
+    // Whether the top-level statement being generated is the last
+    // expression statement, whose value stays on the stack.
+    keep_value: bool,
+    had_error: bool
+}
+
+impl<'generating> CodeGen<'generating> {
+    pub fn new(chunk: &'generating mut Chunk, source_file_path: &'generating str) -> CodeGen<'generating> {
+        CodeGen {
+            chunk,
+            source_file_path,
+            line: 0,
+            span: Span { line: 0, column: 0 },
+            depth: 0,
+            loop_breaks: Vec::new(),
+            keep_value: false,
+            had_error: false
+        }
+    }
+
+    /*
+    * Returns false if the program did not fit; the error has been reported.
+    */
+    pub fn generate(&mut self, program: &[Stmt]) -> bool {
+        let kept: Option<usize> = program.iter().rposition(|stmt| matches!(stmt, Stmt::Expression { .. }));
+        for (idx, stmt) in program.iter().enumerate() {
+            self.keep_value = kept == Some(idx);
+            self.gen_stmt(stmt);
+        }
+        !self.had_error
+    }
+
+    fn gen_stmt(&mut self, stmt: &Stmt) {
+        self.line = stmt.span().line;
+        self.span = stmt.span();
+        match stmt {
+            Stmt::Print { value, .. } => {
+                self.gen_expr(value);
+                self.emit(OpCode::OP_PRINT);
+            },
+            /*
+            * The last top-level expression statement leaves its value on the
+            * stack, for Vm::eval to return and `--dump-stack` to show. Every
+            * other value is popped, so a long program does not fill the
+            * stack with results nobody reads.
+            */
+            Stmt::Expression { expr, .. } => {
+                self.gen_expr(expr);
+                if self.depth > 0 || !self.keep_value {
+                    self.emit(OpCode::OP_POP);
+                }
+            },
+            Stmt::VarDecl { name, initializer, .. } => {
+                match initializer {
+                    Some(initializer) => self.gen_expr(initializer),
+                    None => self.chunk.write_nil(self.line)
+                }
+                self.emit_global(OpCode::OP_DEF_GLOBAL, name);
+            },
+            Stmt::Assign { name, value, .. } => {
+                self.gen_expr(value);
+                self.emit_global(OpCode::OP_SET_GLOBAL, name);
+            },
+            Stmt::If { condition, then_branch, else_branch, span } => {
+                self.gen_expr(condition);
+                let then_jump: usize = self.emit_jump(OpCode::OP_JMP_IF_FALSE);
+                self.gen_block(then_branch);
+                match else_branch {
+                    Some(else_branch) => {
+                        let else_jump: usize = self.emit_jump(OpCode::OP_ELSE);
+                        self.patch_jump(then_jump, *span);
+                        self.gen_stmt(else_branch);
+                        self.patch_jump(else_jump, else_branch.span());
+                    },
+                    None => self.patch_jump(then_jump, *span)
+                }
+            },
+            Stmt::While { condition, body, span } => {
+                let loop_start: usize = self.chunk.code.len();
+                self.gen_expr(condition);
+                let exit_jump: usize = self.emit_jump(OpCode::OP_JMP_IF_FALSE);
+                self.loop_breaks.push(Vec::new());
+                self.gen_block(body);
+                self.line = span.line;
+                self.emit_loop(loop_start, *span);
+                self.patch_jump(exit_jump, *span);
+                for (jump, span) in self.loop_breaks.pop().unwrap_or_default() {
+                    self.patch_jump(jump, span);
+                }
+            },
+            Stmt::Break { span } => {
+                let jump: usize = self.emit_jump(OpCode::OP_ELSE);
+                if let Some(breaks) = self.loop_breaks.last_mut() {
+                    breaks.push((jump, *span));
+                }
+            },
+            Stmt::Block { body, .. } => self.gen_block(body)
+        }
+    }
+
+    #[inline]
+    fn gen_block(&mut self, body: &[Stmt]) {
+        self.depth += 1;
+        for stmt in body {
+            self.gen_stmt(stmt);
+        }
+        self.depth -= 1;
+    }
+
+    fn gen_expr(&mut self, expr: &Expr) {
+        self.line = expr.span().line;
+        self.span = expr.span();
+        match expr {
+            Expr::Literal { value, .. } => {
+                let written: bool = match value {
+                    Literal::Integer(value) => self.chunk.write_const_int(*value, self.line),
+                    Literal::Double(value) => self.chunk.write_const_double(*value, self.line),
+                    Literal::CString(value) => self.chunk.write_cstring(value.clone(), self.line),
+                    Literal::Boolean(value) => {
+                        self.chunk.write_bool(*value, self.line);
+                        true
+                    },
+                    Literal::Nil => {
+                        self.chunk.write_nil(self.line);
+                        true
+                    }
+                };
+                if !written {
+                    self._too_many_constants();
+                }
+            },
+            Expr::Variable { name, .. } => self.emit_global(OpCode::OP_LOAD_GLOBAL, name),
+            Expr::Grouping { inner, .. } => self.gen_expr(inner),
+            Expr::Unary { op, operand, span } => {
+                self.gen_expr(operand);
+                self.line = span.line;
+                match op {
+                    UnaryOp::Negate => self.emit(OpCode::OP_NEGATE),
+                    UnaryOp::Not => self.emit(OpCode::OP_NOT)
+                }
+            },
+            Expr::Binary { op, left, right, span } => {
+                self.gen_expr(left);
+                self.gen_expr(right);
+                self.line = span.line;
+                self.emit(match op {
+                    BinaryOp::Add => OpCode::OP_ADD,
+                    BinaryOp::Subtract => OpCode::OP_SUBTRACT,
+                    BinaryOp::Multiply => OpCode::OP_MULTIPLY,
+                    BinaryOp::Divide => OpCode::OP_DIVIDE,
+                    BinaryOp::Greater => OpCode::OP_GT,
+                    BinaryOp::Less => OpCode::OP_LT,
+                    BinaryOp::Equal => OpCode::OP_EQ_EQ,
+                    BinaryOp::And => OpCode::OP_AND,
+                    BinaryOp::Or => OpCode::OP_OR
+                });
+            },
+            Expr::Call { callee, args, span } => {
+                for arg in args {
+                    self.gen_expr(arg);
+                }
+                self.line = span.line;
+                self.emit_global(OpCode::OP_CALL, callee);
+                self.chunk.write_byte(args.len() as u8, self.line);
+            }
+        }
+    }
+
+    #[inline]
+    fn emit(&mut self, code: OpCode) {
+        self.chunk.write(code, self.line);
+    }
+
+    fn emit_global(&mut self, code: OpCode, name: &str) {
+        let index: u16 = match self.chunk.write_const(Value::from(name)) {
+            Some(index) => index,
+            None => return self._too_many_constants()
+        };
+        self.emit(code);
+        self.chunk.write_short(index, self.line);
+    }
+
+    fn emit_jump(&mut self, code: OpCode) -> usize {
+        self.emit(code);
+        self.chunk.write_short(0xFFFF, self.line);
+        self.chunk.code.len() - 2
+    }
+
+    /*
+    * `span` is the statement whose block the jump crosses, for the error
+    * when it is too long.
+    */
+    fn emit_loop(&mut self, loop_start: usize, span: Span) {
+        self.emit(OpCode::OP_LOOP);
+        let offset: usize = self.chunk.code.len() + 2 - loop_start;
+        if offset > u16::MAX as usize {
+            self._too_long(span);
+        }
+        self.chunk.write_short(offset as u16, self.line);
+    }
+
+    fn patch_jump(&mut self, jump_offset: usize, span: Span) {
+        let jump_op_count: usize = self.chunk.code.len() - jump_offset - 2;
+        if jump_op_count > u16::MAX as usize {
+            self._too_long(span);
+        }
+        self.chunk.code[jump_offset] = ((jump_op_count >> 8) & 0xFF) as u8;
+        self.chunk.code[jump_offset + 1] = (jump_op_count & 0xFF) as u8;
+    }
+
+    fn _too_many_constants(&mut self) {
+        let message: String = format!("program ma {} bhanda badhi constant ra naam bhaye, jati bytecode le rakhna sakdaina. Program lai sano file haru ma chhutyaunus.", u16::MAX as usize + 1);
+        self.error_at(self.span, &message);
+    }
+
+    fn _too_long(&mut self, span: Span) {
+        let message: String = format!("yo block bytecode ma {} bytes bhanda lamo bhayo, jati jump le kataun sakdaina. Block lai sano banaunus.", u16::MAX);
+        self.error_at(span, &message);
+    }
+
+    /*
+    * Only the first error is reported: once a program is too big, every
+    * constant after it would be too.
+    */
+    fn error_at(&mut self, span: Span, message: &str) {
+        if self.had_error {
+            return;
+        }
+        self.had_error = true;
+        let location: String = format!("{}:{}:{}", self.source_file_path, span.line, span.column);
+        diagnostics::print_located(diagnostics::Severity::Error, &format!("Compilation error[{}]", ErrorCode::ProgramTooLarge), message, &location, &diagnostics::explain_hint(ErrorCode::ProgramTooLarge));
+    }
+}
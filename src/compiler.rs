@@ -25,12 +25,11 @@
 #![allow(non_camel_case_types)]
 
 use std::collections::HashMap;
-use std::fs;
 
-use crate::chunk::OpCode;
+use crate::ast::{BinaryOp, Expr, Literal, Span, Stmt, UnaryOp};
 use crate::scanner;
-use crate::chunk;
 use crate::scanner::TokenType;
+use crate::diagnostics::{self, ErrorCode};
 
 #[derive(PartialEq, PartialOrd, Clone, Copy, Hash, Eq, Debug)]
 enum Precedence {
@@ -47,306 +46,498 @@ enum Precedence {
     PREC_PRIMARY
 }
 
+/*
+* Scans and parses `source_code` in one go. None if either reported an error.
+*/
+pub fn parse(source_name: &str, source_code: String) -> Option<Vec<Stmt>> {
+    let mut scanner: scanner::Scanner = scanner::Scanner::new(source_code);
+    let tokens: Vec<scanner::Token> = scanner.start_scan();
+    if scanner.had_error {
+        return None;
+    }
+    Parser::new(source_name.to_owned(), &tokens).parse()
+}
+
+/*
+* Turns the token stream into a list of statements. Syntax errors are
+* reported as they are found; semantic checks (lint::Linter) and bytecode
+* emission (codegen::CodeGen) run over the tree afterwards.
+*/
 pub struct Parser<'compiling> {
     source_file_path: String,
     tokens: &'compiling Vec<scanner::Token>,
-    chunk: &'compiling mut chunk::Chunk,
     current: &'compiling scanner::Token,
     previous: &'compiling scanner::Token,
     counter: usize,
     had_error: bool,
     panic_mode: bool,
-    rules: HashMap<scanner::TokenType, &'compiling (Option<fn(&mut Self)>, Option<fn(&mut Self)>, Precedence)>
+    loop_depth: usize,
+    rules: HashMap<scanner::TokenType, &'compiling ParseRule<'compiling>>
 }
 
+type PrefixFn<'compiling> = fn(&mut Parser<'compiling>) -> Expr;
+type InfixFn<'compiling> = fn(&mut Parser<'compiling>, Expr) -> Expr;
+type ParseRule<'compiling> = (Option<PrefixFn<'compiling>>, Option<InfixFn<'compiling>>, Precedence);
+
 /* 'static-like' method definitions */
 impl<'compiling> Parser<'compiling> {
-    pub fn new(source_file_path: String, tokens: &'compiling Vec<scanner::Token>, chunk: &'compiling mut chunk::Chunk) -> Parser<'compiling> {
+    pub fn new(source_file_path: String, tokens: &'compiling Vec<scanner::Token>) -> Parser<'compiling> {
         Parser {
             source_file_path,
             tokens,
-            chunk,
             current: &tokens[0],
             previous: &tokens[0],
             counter: 0,
             had_error: false,
             panic_mode: false,
+            loop_depth: 0,
             rules: HashMap::from_iter(vec![
-                (scanner::TokenType::TOKEN_LEFT_PAREN, &(Some(Parser::parse_grouping as fn(&mut Self)), None, Precedence::PREC_NONE)),
+                (scanner::TokenType::TOKEN_LEFT_PAREN, &(Some(Parser::parse_grouping as PrefixFn), Some(Parser::parse_call as InfixFn), Precedence::PREC_CALL)),
                 (scanner::TokenType::TOKEN_RIGHT_PAREN, &(None, None, Precedence::PREC_NONE)),
+                (scanner::TokenType::TOKEN_COMMA, &(None, None, Precedence::PREC_NONE)),
                 (scanner::TokenType::TOKEN_LEFT_BRACE, &(None, None, Precedence::PREC_NONE)),
                 (scanner::TokenType::TOKEN_RIGHT_BRACE, &(None, None, Precedence::PREC_NONE)),
-                (scanner::TokenType::TOKEN_FLOAT_NUM, &(Some(Parser::parse_number as fn(&mut Self)), None, Precedence::PREC_NONE)),
-                (scanner::TokenType::TOKEN_INT_NUM, &(Some(Parser::parse_number as fn(&mut Self)), None, Precedence::PREC_NONE)),
-                (scanner::TokenType::TOKEN_PLUS, &(None, Some(Parser::parse_binary as fn(&mut Self)), Precedence::PREC_TERM)),
-                (scanner::TokenType::TOKEN_MINUS, &(Some(Parser::parse_unary as fn(&mut Self)), Some(Parser::parse_binary as fn(&mut Self)), Precedence::PREC_TERM)),
-                (scanner::TokenType::TOKEN_SLASH, &(None, Some(Parser::parse_binary as fn(&mut Self)), Precedence::PREC_FACTOR)),
-                (scanner::TokenType::TOKEN_STAR, &(None, Some(Parser::parse_binary as fn(&mut Self)), Precedence::PREC_FACTOR)),
-                (scanner::TokenType::TOKEN_THULO, &(None, Some(Parser::parse_binary as fn(&mut Self)), Precedence::PREC_COMPARISON)),
-                (scanner::TokenType::TOKEN_SANO, &(None, Some(Parser::parse_binary as fn(&mut Self)), Precedence::PREC_COMPARISON)),
-                (scanner::TokenType::TOKEN_BARABAR, &(None, Some(Parser::parse_binary as fn(&mut Self)), Precedence::PREC_EQUALITY)),
-                (scanner::TokenType::TOKEN_SAHI, &(Some(Parser::parse_literal as fn(&mut Self)), None, Precedence::PREC_NONE)),
-                (scanner::TokenType::TOKEN_GALAT, &(Some(Parser::parse_literal as fn(&mut Self)), None, Precedence::PREC_NONE)),
-                (scanner::TokenType::TOKEN_NIL, &(Some(Parser::parse_literal as fn(&mut Self)), None, Precedence::PREC_NONE)),
-                (scanner::TokenType::TOKEN_STRING, &(Some(Parser::parse_string as fn(&mut Self)), None, Precedence::PREC_NONE)),
-                (scanner::TokenType::TOKEN_CHHAINA, &(Some(Parser::parse_unary as fn(&mut Self)), None, Precedence::PREC_UNARY)),
+                (scanner::TokenType::TOKEN_FLOAT_NUM, &(Some(Parser::parse_number as PrefixFn), None, Precedence::PREC_NONE)),
+                (scanner::TokenType::TOKEN_INT_NUM, &(Some(Parser::parse_number as PrefixFn), None, Precedence::PREC_NONE)),
+                (scanner::TokenType::TOKEN_PLUS, &(None, Some(Parser::parse_binary as InfixFn), Precedence::PREC_TERM)),
+                (scanner::TokenType::TOKEN_MINUS, &(Some(Parser::parse_unary as PrefixFn), Some(Parser::parse_binary as InfixFn), Precedence::PREC_TERM)),
+                (scanner::TokenType::TOKEN_SLASH, &(None, Some(Parser::parse_binary as InfixFn), Precedence::PREC_FACTOR)),
+                (scanner::TokenType::TOKEN_STAR, &(None, Some(Parser::parse_binary as InfixFn), Precedence::PREC_FACTOR)),
+                (scanner::TokenType::TOKEN_THULO, &(None, Some(Parser::parse_binary as InfixFn), Precedence::PREC_COMPARISON)),
+                (scanner::TokenType::TOKEN_SANO, &(None, Some(Parser::parse_binary as InfixFn), Precedence::PREC_COMPARISON)),
+                (scanner::TokenType::TOKEN_BARABAR, &(None, Some(Parser::parse_binary as InfixFn), Precedence::PREC_EQUALITY)),
+                (scanner::TokenType::TOKEN_AND, &(None, Some(Parser::parse_binary as InfixFn), Precedence::PREC_AND)),
+                (scanner::TokenType::TOKEN_OR, &(None, Some(Parser::parse_binary as InfixFn), Precedence::PREC_OR)),
+                (scanner::TokenType::TOKEN_SAHI, &(Some(Parser::parse_literal as PrefixFn), None, Precedence::PREC_NONE)),
+                (scanner::TokenType::TOKEN_GALAT, &(Some(Parser::parse_literal as PrefixFn), None, Precedence::PREC_NONE)),
+                (scanner::TokenType::TOKEN_NIL, &(Some(Parser::parse_literal as PrefixFn), None, Precedence::PREC_NONE)),
+                (scanner::TokenType::TOKEN_STRING, &(Some(Parser::parse_string as PrefixFn), None, Precedence::PREC_NONE)),
+                (scanner::TokenType::TOKEN_CHHAINA, &(Some(Parser::parse_unary as PrefixFn), None, Precedence::PREC_UNARY)),
                 (scanner::TokenType::TOKEN_DEKHAU, &(None, None, Precedence::PREC_NONE)),
                 (scanner::TokenType::TOKEN_SEMICOLON, &(None, None, Precedence::PREC_NONE)),
                 (scanner::TokenType::TOKEN_RAKHA, &(None, None, Precedence::PREC_NONE)),
                 (scanner::TokenType::TOKEN_MA, &(None, None, Precedence::PREC_NONE)),
-                (scanner::TokenType::TOKEN_IDENTIFIER, &(Some(Parser::parse_variable as fn(&mut Self)), None, Precedence::PREC_NONE)),
+                (scanner::TokenType::TOKEN_IDENTIFIER, &(Some(Parser::parse_variable as PrefixFn), None, Precedence::PREC_NONE)),
+                (scanner::TokenType::TOKEN_YADI, &(None, None, Precedence::PREC_NONE)),
+                (scanner::TokenType::TOKEN_NATRA, &(None, None, Precedence::PREC_NONE)),
+                (scanner::TokenType::TOKEN_GHUMAU, &(None, None, Precedence::PREC_NONE)),
+                (scanner::TokenType::TOKEN_PATAK, &(None, None, Precedence::PREC_NONE)),
                 (scanner::TokenType::TOKEN_NONE, &(None, None, Precedence::PREC_NONE)),
             ])
         }
     }
 }
 
-#[derive(PartialEq, Eq)]
-pub enum CompilationResult {
-    Ok,
-    Error
-}
-
 impl<'compiling> Parser<'compiling> {
     fn debugg(&self, fn_name: &str) {
         println!("DEBUG[{}]: current token = {:?}", fn_name, self.current.token_type);
         println!("DEBUG[{}]: previous token = {:?}", fn_name, self.previous.token_type);
     }
 
-    #[inline]
-    pub fn compile(&mut self) -> CompilationResult {
-        // self.advance();
+    /*
+    * Returns None if any syntax error was reported.
+    */
+    pub fn parse(&mut self) -> Option<Vec<Stmt>> {
+        let mut program: Vec<Stmt> = Vec::new();
         while !self._match(&scanner::TokenType::TOKEN_NONE) {
-            self._parse_decl_stmt();
+            if self._match(&TokenType::TOKEN_RIGHT_BRACE) {
+                self.error_at_current(ErrorCode::UnmatchedBrace, "yo '}' kunai '{' sanga milena.");
+                self._sync_err();
+                continue;
+            }
+            if let Some(stmt) = self._parse_decl_stmt() {
+                program.push(stmt);
+            }
             if self.panic_mode{ self._sync_err(); }
         }
-        if self.had_error { CompilationResult::Error }
-        else { CompilationResult::Ok }
+        if self.had_error { None }
+        else { Some(program) }
     }
 
     #[inline]
-    fn _parse_decl_stmt(&mut self) {
-        self.advance();
-        if self.previous.token_type == TokenType::TOKEN_RAKHA {
-            self._parse_var_decl_stmt();
+    fn _parse_decl_stmt(&mut self) -> Option<Stmt> {
+        if self._match(&TokenType::TOKEN_RAKHA) {
+            self._parse_var_decl_stmt()
         }
         else { 
-            self._parse_stmt(); 
+            self._parse_stmt()
         }
     }
 
-    fn _parse_var_decl_stmt(&mut self) {
+    fn _parse_var_decl_stmt(&mut self) -> Option<Stmt> {
         if !self._match(&scanner::TokenType::TOKEN_IDENTIFIER) {
-            self.error_at_current(&format!("'rakha' lekhe pachhi tapaile variable ko naam dina parne hunchha. '{}' chai aasha gariyeko thiyena.", self.current.lexeme));
-            return;
+            self.error_at_current(ErrorCode::ExpectedVariableName, &format!("'rakha' lekhe pachhi tapaile variable ko naam dina parne hunchha. '{}' chai aasha gariyeko thiyena.", self.current.lexeme));
+            return None;
         }
+        let name_token: &scanner::Token = self.previous;
         let var_name: &String = &self.previous.lexeme;
+        let mut initializer: Option<Expr> = None;
         if self._match(&TokenType::TOKEN_MA) {
-            self.parse_expression();
-            self.consume(TokenType::TOKEN_SEMICOLON, "Tapaile sayed 'rakha' statement lai antya garna ';' lekhna chhutaunu bhayo hola.");
+            initializer = Some(self.parse_expression());
+            self.consume(TokenType::TOKEN_SEMICOLON, ErrorCode::MissingSemicolon, "Tapaile sayed 'rakha' statement lai antya garna ';' lekhna chhutaunu bhayo hola.");
         }
-        else {
-            self.emit_bytecode(chunk::OpCode::OP_NIL as u8);
-            if !self._match(&TokenType::TOKEN_SEMICOLON) {
-                if self.current.token_type == scanner::TokenType::TOKEN_NONE {
-                    self.consume(TokenType::TOKEN_SEMICOLON, &format!("Yadi '{}' ma kae value rakhnu chhaina bhane ';' lekhnus.", var_name));
-                    return;
-                }
-                self.consume(TokenType::TOKEN_SEMICOLON, &format!("Yadi '{}' ma kae value rakhnu chhaina bhane ';' lekhnus. '{}' chai aasha gariyeko thiyena.", var_name, self.current.lexeme));
+        else if !self._match(&TokenType::TOKEN_SEMICOLON) {
+            if self.current.token_type == scanner::TokenType::TOKEN_NONE {
+                self.consume(TokenType::TOKEN_SEMICOLON, ErrorCode::MissingSemicolon, &format!("Yadi '{}' ma kae value rakhnu chhaina bhane ';' lekhnus.", var_name));
+            }
+            else {
+                self.consume(TokenType::TOKEN_SEMICOLON, ErrorCode::MissingSemicolon, &format!("Yadi '{}' ma kae value rakhnu chhaina bhane ';' lekhnus. '{}' chai aasha gariyeko thiyena.", var_name, self.current.lexeme));
             }
         }
-        self.chunk.write_const(chunk::PrimType::CString(var_name.len(), var_name.clone()));
-        self.emit_bytecode(chunk::OpCode::OP_DEF_GLOBAL as u8);
+        Some(Stmt::VarDecl {
+            name: var_name.clone(),
+            initializer,
+            span: Span::of(name_token)
+        })
+    }
+
+    fn _parse_assign_stmt(&mut self) -> Option<Stmt> {
+        let name_token: &scanner::Token = self.previous;
+        let var_name: &String = &self.previous.lexeme;
+        self.advance();
+        let value: Expr = self.parse_expression();
+        self.consume(TokenType::TOKEN_SEMICOLON, ErrorCode::MissingSemicolon, &format!("Tapaile sayed '{}' ma value rakhe pachhi ';' lekhna chhutaunu bhayo hola.", var_name));
+        Some(Stmt::Assign {
+            name: var_name.clone(),
+            value,
+            span: Span::of(name_token)
+        })
     }
 
     #[inline]
-    fn _parse_stmt(&mut self) {
-        if self.previous.token_type == scanner::TokenType::TOKEN_DEKHAU {
-            self._parse_print_stmt();  
+    fn _parse_stmt(&mut self) -> Option<Stmt> {
+        if self._match(&scanner::TokenType::TOKEN_DEKHAU) {
+            self._parse_print_stmt()
+        }
+        else if self._match(&scanner::TokenType::TOKEN_YADI) {
+            self._parse_if_stmt()
+        }
+        else if self._match(&scanner::TokenType::TOKEN_GHUMAU) {
+            self._parse_ghumau_stmt()
         }
-        else if self.previous.token_type == scanner::TokenType::TOKEN_YADI {
-            self._parse_if_stmt();
+        else if self._match(&scanner::TokenType::TOKEN_PATAK) {
+            self._parse_patak_stmt()
+        }
+        else if self._match(&scanner::TokenType::TOKEN_NATRA) {
+            self.error_at_current(ErrorCode::NatraWithoutYadi, "'natra' lai 'yadi' block pachhi matra lekhna milchha.");
+            // Still parse the block, so its '}' is not reported as unmatched.
+            if self._match(&TokenType::TOKEN_LEFT_BRACE) {
+                self.panic_mode = false;
+                self._parse_block_stmt();
+            }
+            None
         }
-        else if self.previous.token_type == scanner::TokenType::TOKEN_NATRA {
-            self._parse_natra_stmt();
+        else if self._check(TokenType::TOKEN_IDENTIFIER) && self._peek_next() == TokenType::TOKEN_MA {
+            self.advance();
+            self._parse_assign_stmt()
         }
-        else if self.previous.token_type == scanner::TokenType::TOKEN_LEFT_BRACE {
-            self._parse_block_stmt();
+        else if self._match(&scanner::TokenType::TOKEN_LEFT_BRACE) {
+            let span: Span = Span::of(self.previous);
+            let body: Vec<Stmt> = self._parse_block_stmt();
+            Some(Stmt::Block { body, span })
         }
         else {
-            self._parse_expr_stmt();
+            self._parse_expr_stmt()
         }
     }
 
-    fn _parse_if_stmt(&mut self) {
-        self.parse_expression();
-        self.emit_bytecode(chunk::OpCode::OP_JMP_IF_FALSE as u8);
-        self.emit_bytecode(0xFF);
-        self.emit_bytecode(0xFF);
-        let jump_offset: usize = self.chunk.code.len() - 2;
-        self.advance();
-        self._parse_block_stmt();
-        let jump_op_count: usize = self.chunk.code.len() - jump_offset - 2;
-        self.chunk.code[jump_offset] = ((jump_op_count >> 8) & 0xFF) as u8;
-        self.chunk.code[jump_offset + 1] = (jump_op_count & 0xFF) as u8;
+    fn _parse_if_stmt(&mut self) -> Option<Stmt> {
+        let span: Span = Span::of(self.previous);
+        let condition: Expr = self.parse_expression();
+        if !self._consume_block_start("yadi") {
+            return None;
+        }
+        let then_branch: Vec<Stmt> = self._parse_block_stmt();
+        let mut else_branch: Option<Box<Stmt>> = None;
+        if self._match(&TokenType::TOKEN_NATRA) {
+            else_branch = self._parse_natra_stmt().map(Box::new);
+        }
+        Some(Stmt::If {
+            condition,
+            then_branch,
+            else_branch,
+            span
+        })
     }
 
-    fn _parse_natra_stmt(&mut self) {
-        self.emit_bytecode(chunk::OpCode::OP_ELSE as u8);
-        self.emit_bytecode(0xFF);
-        self.emit_bytecode(0xFF);
-        let jump_offset: usize = self.chunk.code.len() - 2;
-        self.advance();
-        self._parse_block_stmt();
-        let jump_op_count: usize = self.chunk.code.len() - jump_offset - 2;
-        self.chunk.code[jump_offset] = ((jump_op_count >> 8) & 0xFF) as u8;
-        self.chunk.code[jump_offset + 1] = (jump_op_count & 0xFF) as u8;
+    fn _parse_natra_stmt(&mut self) -> Option<Stmt> {
+        if self._match(&TokenType::TOKEN_YADI) {
+            return self._parse_if_stmt();
+        }
+        if !self._consume_block_start("natra") {
+            return None;
+        }
+        let span: Span = Span::of(self.previous);
+        let body: Vec<Stmt> = self._parse_block_stmt();
+        Some(Stmt::Block { body, span })
+    }
+
+    fn _parse_ghumau_stmt(&mut self) -> Option<Stmt> {
+        let span: Span = Span::of(self.previous);
+        let condition: Expr = self.parse_expression();
+        if !self._consume_block_start("ghumau") {
+            return None;
+        }
+        self.loop_depth += 1;
+        let body: Vec<Stmt> = self._parse_block_stmt();
+        self.loop_depth -= 1;
+        Some(Stmt::While {
+            condition,
+            body,
+            span
+        })
     }
-    
-    fn emit_jump_bytecode(&mut self, code: chunk::OpCode) -> usize {
-        self.emit_bytecode(code as u8);
-        self.emit_bytecode(0xFF);
-        self.emit_bytecode(0xFF);
-        (self.chunk.code.len() - 2)
+
+    /*
+    * Without a '{' the body is not parsed as a block at all; otherwise the
+    * rest of the file would be swallowed looking for its '}'.
+    */
+    fn _consume_block_start(&mut self, keyword: &str) -> bool {
+        if self._match(&TokenType::TOKEN_LEFT_BRACE) {
+            return true;
+        }
+        self.error_at(self.counter + 1, ErrorCode::ExpectedBlock, &format!("'{}' pachhi '{{' lekhnus. '{}' chai aasha gariyeko thiyena.", keyword, self.current.lexeme));
+        false
     }
 
-    fn patch_jump_stmt(&mut self, jump_offset: usize) {
-        let jump_op_count: usize = self.chunk.code.len() - jump_offset - 2;
-        self.chunk.code[jump_offset] = ((jump_op_count >> 8) & 0xFF) as u8;
-        self.chunk.code[jump_offset + 1] = (jump_op_count & 0xFF) as u8;
+    fn _parse_patak_stmt(&mut self) -> Option<Stmt> {
+        let span: Span = Span::of(self.previous);
+        if self.loop_depth == 0 {
+            self.error_at_current(ErrorCode::PatakOutsideLoop, "'patak' lai 'ghumau' bhitra matra lekhna milchha.");
+            return None;
+        }
+        self.consume(TokenType::TOKEN_SEMICOLON, ErrorCode::MissingSemicolon, "Tapaile sayed 'patak' pachhi ';' lekhna chhutaunu bhayo hola.");
+        Some(Stmt::Break { span })
     }
 
-    fn _parse_block_stmt(&mut self) {
+    fn _parse_block_stmt(&mut self) -> Vec<Stmt> {
+        let mut body: Vec<Stmt> = Vec::new();
         while !self._check(TokenType::TOKEN_RIGHT_BRACE) && !self._check(TokenType::TOKEN_NONE) {
-            self._parse_decl_stmt();
+            if let Some(stmt) = self._parse_decl_stmt() {
+                body.push(stmt);
+            }
+            if self.panic_mode { self._sync_err(); }
         } 
-        self.consume(TokenType::TOKEN_RIGHT_BRACE, "'{' lekhisake pachhi '}' pani lekhnus.");
+        self.consume(TokenType::TOKEN_RIGHT_BRACE, ErrorCode::UnclosedBlock, "'{' lekhisake pachhi '}' pani lekhnus.");
+        body
     }
 
     #[inline]
-    fn _parse_expr_stmt(&mut self) {
-        self.parse_expression();
-        self.consume(scanner::TokenType::TOKEN_SEMICOLON, format!("Tapaile sayed '{}' pachhi ';' lekhna chhutaunu bhayo hola.", self.previous.lexeme).as_str());
-        // self.emit_bytecode(chunk::OpCode::OP_POP as u8);
+    fn _parse_expr_stmt(&mut self) -> Option<Stmt> {
+        let first: &scanner::Token = self.current;
+        let expr: Expr = self.parse_expression();
+        if first.token_type == TokenType::TOKEN_IDENTIFIER && !self._check(TokenType::TOKEN_SEMICOLON) {
+            let keywords = scanner::KEYWORDS.iter().map(|(keyword, _)| *keyword);
+            if let Some(keyword) = diagnostics::suggest(&first.lexeme, keywords) {
+                self.error_at_current(ErrorCode::MisspelledKeyword, &format!("'{}' bhanne keyword chhaina. {}", first.lexeme, diagnostics::did_you_mean(keyword)));
+                return None;
+            }
+        }
+        self.consume(scanner::TokenType::TOKEN_SEMICOLON, ErrorCode::MissingSemicolon, format!("Tapaile sayed '{}' pachhi ';' lekhna chhutaunu bhayo hola.", self.previous.lexeme).as_str());
+        Some(Stmt::Expression {
+            expr,
+            span: Span::of(first)
+        })
     }
 
-    fn _parse_print_stmt(&mut self) {
-        self.parse_expression();
-        self.consume(TokenType::TOKEN_SEMICOLON, "Tapaile sayed dekhau statement sakiye pachhi ';' lekhna chhutaunu bhayo hola.");
-        self.emit_bytecode(chunk::OpCode::OP_PRINT as u8);
+    fn _parse_print_stmt(&mut self) -> Option<Stmt> {
+        let span: Span = Span::of(self.previous);
+        let value: Expr = self.parse_expression();
+        self.consume(TokenType::TOKEN_SEMICOLON, ErrorCode::MissingSemicolon, "Tapaile sayed dekhau statement sakiye pachhi ';' lekhna chhutaunu bhayo hola.");
+        Some(Stmt::Print { value, span })
     }
 
+    /*
+    * Leaves panic mode by skipping tokens until the start of the next
+    * statement: just past a ';', or right before a statement keyword or
+    * the '}' closing the enclosing block. It is only called between
+    * statements, so an error is never followed by more errors from the rest
+    * of the same statement.
+    */
     fn _sync_err(&mut self) {
         self.panic_mode = false;
         while self.current.token_type != scanner::TokenType::TOKEN_NONE {
-            if self.current.token_type == scanner::TokenType::TOKEN_SEMICOLON { return; }
+            if self.previous.token_type == scanner::TokenType::TOKEN_SEMICOLON { return; }
             match self.current.token_type {
-                scanner::TokenType::TOKEN_DEKHAU | scanner::TokenType::TOKEN_GHUMAU => return,
+                scanner::TokenType::TOKEN_RAKHA |
+                scanner::TokenType::TOKEN_DEKHAU |
+                scanner::TokenType::TOKEN_YADI |
+                scanner::TokenType::TOKEN_GHUMAU |
+                scanner::TokenType::TOKEN_PATAK |
+                scanner::TokenType::TOKEN_RIGHT_BRACE => return,
                 _ => ()
             }
             self.advance();
         }
-        self.panic_mode = false;
     }
 
     #[inline]
-    fn parse_expression(&mut self) {
-        self.parse_precedence(Precedence::PREC_ASSIGNMENT);
+    fn parse_expression(&mut self) -> Expr {
+        self.parse_precedence(Precedence::PREC_ASSIGNMENT)
     }
 
-    fn parse_precedence(&mut self, prec: Precedence) {
+    fn parse_precedence(&mut self, prec: Precedence) -> Expr {
         let now: &scanner::Token = self.previous;
-        self.advance();
-        let prefix = self.get_rule(self.previous.token_type);
-        if let Some(func_tuple) = prefix {
-            if let Some(prefix_func) = func_tuple.0 {
-                prefix_func(self);
+        let prefix: Option<PrefixFn<'compiling>> = self.get_rule(self.current.token_type).and_then(|rule| rule.0);
+        let mut expr: Expr = match prefix {
+            Some(prefix_func) => {
+                self.advance();
+                prefix_func(self)
+            },
+            None => {
+                // The offending token is left for _sync_err, so a stray '}' still closes its block.
+                let message: String = match self.current.token_type {
+                    TokenType::TOKEN_NONE => format!("'{}' pachhi expression dinus, tara file nai sakiyo.", now.lexeme),
+                    _ => format!("'{}' pachhi expression dinus. '{}' chai aasha gariyeko thiyena.", now.lexeme, self.current.lexeme)
+                };
+                self.error_at(self.counter + 1, ErrorCode::ExpectedExpression, &message);
+                return Expr::Literal {
+                    value: Literal::Nil,
+                    span: Span::of(self.current)
+                };
             }
-            // else
-            // {
-            //     self.error_at(self.counter - 1, &format!("'{}' pachhadi expression dinus.", now.lexeme));
-            // }
-        }
+        };
 
-        // maybe prec has to be reassigned?
-        while prec < self.get_rule(self.current.token_type).unwrap().2 {
+        // Strictly greater: an operator of the same precedence is left for the
+        // caller, which makes binary operators left-associative.
+        while prec < self.get_rule(self.current.token_type).map_or(Precedence::PREC_NONE, |rule| rule.2) {
             self.advance();
-            let infix = self.get_rule(self.previous.token_type);
-            if let Some(func_tuple) = infix {
-                if let Some(infix_func) = func_tuple.1 {
-                    infix_func(self);
-                }
+            let infix: Option<InfixFn<'compiling>> = self.get_rule(self.previous.token_type).and_then(|rule| rule.1);
+            if let Some(infix_func) = infix {
+                expr = infix_func(self, expr);
             }
         }
+        expr
     }
 
-    fn parse_literal(&mut self) {
-        match self.previous.token_type {
-            scanner::TokenType::TOKEN_SAHI => self.chunk.write_bool(true),
-            scanner::TokenType::TOKEN_GALAT => self.chunk.write_bool(false),
-            scanner::TokenType::TOKEN_NIL => self.chunk.write_nil(),
-            _ => ()
+    fn parse_literal(&mut self) -> Expr {
+        let value: Literal = match self.previous.token_type {
+            scanner::TokenType::TOKEN_SAHI => Literal::Boolean(true),
+            scanner::TokenType::TOKEN_GALAT => Literal::Boolean(false),
+            _ => Literal::Nil
+        };
+        Expr::Literal {
+            value,
+            span: Span::of(self.previous)
         }
     }
 
     #[inline]
-    fn parse_string(&mut self) {
-        self.chunk.write_cstring(self.previous.literal.clone().unwrap());
+    fn parse_string(&mut self) -> Expr {
+        Expr::Literal {
+            value: Literal::CString(self.previous.literal.clone().unwrap()),
+            span: Span::of(self.previous)
+        }
     }
 
-    fn parse_variable(&mut self) {
-        self.chunk.write_const(chunk::PrimType::CString(self.previous.lexeme.len(), self.previous.lexeme.clone()));
-        self.emit_bytecode(OpCode::OP_LOAD_GLOBAL as u8); 
+    fn parse_variable(&mut self) -> Expr {
+        Expr::Variable {
+            name: self.previous.lexeme.clone(),
+            span: Span::of(self.previous)
+        }
     }
 
-    fn parse_number(&mut self) {
+    fn parse_number(&mut self) -> Expr {
         let token: &scanner::Token = self.previous;
-        match token.token_type {
-            scanner::TokenType::TOKEN_FLOAT_NUM => {
-                self.chunk.write_const_double(token.lexeme.parse().unwrap());
-            },
-            scanner::TokenType::TOKEN_INT_NUM => {
-                self.chunk.write_const_int(token.lexeme.parse().unwrap());
+        let value: Literal = match token.token_type {
+            scanner::TokenType::TOKEN_FLOAT_NUM => Literal::Double(token.lexeme.parse().unwrap()),
+            _ => match token.lexeme.parse() {
+                Ok(value) => Literal::Integer(value),
+                Err(_) => {
+                    let message: String = format!("'{}' 64 bit int ma atauna sakine bhanda thulo sankhya ho. Float chahiye '{}.0' lekhnus.", token.lexeme, token.lexeme);
+                    // The number is the previous token.
+                    self.error_at(self.counter, ErrorCode::IntegerOverflow, &message);
+                    Literal::Integer(0)
+                }
             }
-            _ => ()
+        };
+        Expr::Literal {
+            value,
+            span: Span::of(token)
         }
     }
 
-    fn parse_binary(&mut self) {
-        let ttype: scanner::TokenType = self.previous.token_type;
-        let prec: Precedence = self.get_rule(ttype).unwrap().2;
-        self.parse_precedence(prec);
-        match ttype {
-            scanner::TokenType::TOKEN_PLUS => self.emit_bytecode(chunk::OpCode::OP_ADD as u8),
-            scanner::TokenType::TOKEN_MINUS => self.emit_bytecode(chunk::OpCode::OP_SUBTRACT as u8),
-            scanner::TokenType::TOKEN_STAR => self.emit_bytecode(chunk::OpCode::OP_MULTIPLY as u8),
-            scanner::TokenType::TOKEN_SLASH => self.emit_bytecode(chunk::OpCode::OP_DIVIDE as u8),
-            scanner::TokenType::TOKEN_THULO => self.emit_bytecode(chunk::OpCode::OP_GT as u8),
-            scanner::TokenType::TOKEN_SANO => self.emit_bytecode(chunk::OpCode::OP_LT as u8),
-            scanner::TokenType::TOKEN_BARABAR => self.emit_bytecode(chunk::OpCode::OP_EQ_EQ as u8),
-            _ => ()
+    fn parse_binary(&mut self, left: Expr) -> Expr {
+        let token: &scanner::Token = self.previous;
+        let prec: Precedence = self.get_rule(token.token_type).map_or(Precedence::PREC_NONE, |rule| rule.2);
+        let right: Expr = self.parse_precedence(prec);
+        let op: BinaryOp = match token.token_type {
+            scanner::TokenType::TOKEN_PLUS => BinaryOp::Add,
+            scanner::TokenType::TOKEN_MINUS => BinaryOp::Subtract,
+            scanner::TokenType::TOKEN_STAR => BinaryOp::Multiply,
+            scanner::TokenType::TOKEN_SLASH => BinaryOp::Divide,
+            scanner::TokenType::TOKEN_THULO => BinaryOp::Greater,
+            scanner::TokenType::TOKEN_SANO => BinaryOp::Less,
+            scanner::TokenType::TOKEN_BARABAR => BinaryOp::Equal,
+            scanner::TokenType::TOKEN_AND => BinaryOp::And,
+            _ => BinaryOp::Or
+        };
+        Expr::Binary {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+            span: Span::of(token)
         }
     }
 
-    fn parse_unary(&mut self) {
+    fn parse_unary(&mut self) -> Expr {
         let token: &scanner::Token = self.previous;
-        self.parse_precedence(Precedence::PREC_UNARY);
-        match token.token_type {
-            scanner::TokenType::TOKEN_MINUS => self.emit_bytecode(chunk::OpCode::OP_NEGATE as u8),
-            scanner::TokenType::TOKEN_CHHAINA => self.emit_bytecode(chunk::OpCode::OP_NOT as u8),
-            _ => ()
+        let operand: Expr = self.parse_precedence(Precedence::PREC_UNARY);
+        let op: UnaryOp = match token.token_type {
+            scanner::TokenType::TOKEN_MINUS => UnaryOp::Negate,
+            _ => UnaryOp::Not
+        };
+        Expr::Unary {
+            op,
+            operand: Box::new(operand),
+            span: Span::of(token)
+        }
+    }
+
+    fn parse_grouping(&mut self) -> Expr {
+        let span: Span = Span::of(self.previous);
+        let inner: Expr = self.parse_expression();
+        self.consume(scanner::TokenType::TOKEN_RIGHT_PAREN, ErrorCode::UnclosedGrouping, "Tapaile sayed '(' lekhi sake pachhi, teslai antya garna ')' lekhna chhutaunu bhayo hola.");
+        Expr::Grouping {
+            inner: Box::new(inner),
+            span
         }
     }
 
-    fn parse_grouping(&mut self) {
-        self.parse_expression();
-        self.consume(scanner::TokenType::TOKEN_RIGHT_PAREN, "Tapaile sayed '(' lekhi sake pachhi, teslai antya garna ')' lekhna chhutaunu bhayo hola.");
+    fn parse_call(&mut self, callee: Expr) -> Expr {
+        // error_at takes the index after the token; this is the '('.
+        let paren: usize = self.counter - 1;
+        let mut args: Vec<Expr> = Vec::new();
+        if !self._check(TokenType::TOKEN_RIGHT_PAREN) {
+            loop {
+                args.push(self.parse_expression());
+                if !self._match(&TokenType::TOKEN_COMMA) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::TOKEN_RIGHT_PAREN, ErrorCode::UnclosedGrouping, "Function call ko argument haru pachhi ')' lekhnus.");
+        let (name, span): (String, Span) = match callee {
+            Expr::Variable { name, span } => (name, span),
+            _ => {
+                self.error_at(paren, ErrorCode::InvalidCall, "Function lai usko naam le matra call garna milchha, jastai 'naam(1, 2)'.");
+                return Expr::Literal { value: Literal::Nil, span: callee.span() };
+            }
+        };
+        if args.len() > u8::MAX as usize {
+            self.error_at(paren, ErrorCode::InvalidCall, &format!("'{}' lai {} wata argument diyiyo; {} bhanda badhi dina mildaina.", name, args.len(), u8::MAX));
+        }
+        Expr::Call {
+            callee: name,
+            args,
+            span
+        }
     }
 
     #[inline]
-    fn get_rule(&self, token_type: scanner::TokenType) -> Option<&(Option<fn(&mut Self)>, Option<fn(&mut Self)>, Precedence)> {
+    fn get_rule(&self, token_type: scanner::TokenType) -> Option<&ParseRule<'compiling>> {
         // println!("DEBUG[get_rule]: TokenType = {:?}", token_type);
-        Some(self.rules[&token_type])
+        self.rules.get(&token_type).copied()
     }
 
     /*
@@ -363,17 +554,12 @@ impl<'compiling> Parser<'compiling> {
     }
 
     #[inline]
-    fn consume(&mut self, token_type: scanner::TokenType, msg: &str) {
+    fn consume(&mut self, token_type: scanner::TokenType, code: ErrorCode, msg: &str) {
         if token_type == self.current.token_type {
             self.advance();
             return;
         }
-        self.error_at_current(msg);
-    }
-
-    #[inline]
-    fn emit_bytecode(&mut self, byte: u8) {
-        self.chunk.write(chunk::OpCode::from_u8(byte));
+        self.error_at_current(code, msg);
     }
 
     #[inline]
@@ -391,16 +577,30 @@ impl<'compiling> Parser<'compiling> {
     }
 
     #[inline]
-    fn error_at_current(&mut self, message: &str) {
-        self.error_at(self.counter, message);
+    fn _peek_next(&self) -> TokenType {
+        match self.tokens.get(self.counter + 1) {
+            Some(token) => token.token_type,
+            None => TokenType::TOKEN_NONE
+        }
     }
 
-    fn error_at(&mut self, token_idx: usize, message: &str) {
-        let token: &scanner::Token = &self.tokens[token_idx - 1];
-        print!("\x1b[1;31mCompilation error\x1b[0;37m: {}", message);
-        println!("\n  \x1b[1;34m-->\x1b[0;37m {}:{}:{}", self.source_file_path, token.line, token.column);
-        println!("\n\x1b[0;37m");
-        self._sync_err();
+    #[inline]
+    fn error_at_current(&mut self, code: ErrorCode, message: &str) {
+        self.error_at(self.counter, code, message);
+    }
+
+    /*
+    * Only the first error of a statement is reported; the rest are
+    * suppressed until _sync_err finds the next statement.
+    */
+    fn error_at(&mut self, token_idx: usize, code: ErrorCode, message: &str) {
         self.had_error = true;
+        if self.panic_mode {
+            return;
+        }
+        self.panic_mode = true;
+        let token: &scanner::Token = &self.tokens[token_idx - 1];
+        let location: String = format!("{}:{}:{}", self.source_file_path, token.line, token.column);
+        diagnostics::print_located(diagnostics::Severity::Error, &format!("Compilation error[{}]", code), message, &location, &diagnostics::explain_hint(code));
     }
 }
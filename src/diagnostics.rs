@@ -0,0 +1,933 @@
+// MIT License
+//
+// Copyright (c) 2023 Ramesh Poudel
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+#![allow(dead_code)]
+#![allow(clippy::new_without_default)]
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::IsTerminal;
+
+/*
+* Every diagnostic bish reports carries one of these codes. The numeric
+* value doubles as the process exit code, so never renumber a variant;
+* retire it and add a new one instead.
+*/
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ErrorCode {
+    UnknownConstant = 2,
+    LogicalOperandType = 3,
+    UnterminatedString = 4,
+    BinaryOperandType = 7,
+    NegateOperandType = 8,
+    NotOperandType = 9,
+    UnprintableValue = 10,
+    MissingSourceFile = 12,
+    UnreadableFile = 15,
+    UndefinedVariable = 18,
+    ExpectedVariableName = 19,
+    MissingSemicolon = 20,
+    UnclosedBlock = 21,
+    UnclosedGrouping = 22,
+    MisspelledKeyword = 23,
+    NatraWithoutYadi = 24,
+    PatakOutsideLoop = 25,
+    ExpectedBlock = 26,
+    ExpectedExpression = 27,
+    UnmatchedBrace = 28,
+    CorruptBytecode = 29,
+    UnsupportedBytecodeVersion = 30,
+    UnwritableFile = 31,
+    InvalidBytecode = 32,
+    UnknownOption = 33,
+    UndefinedFunction = 34,
+    WrongArgumentCount = 35,
+    NativeFunctionError = 36,
+    InvalidCall = 37,
+    LimitExceeded = 38,
+    Cancelled = 39,
+    CapabilityDenied = 40,
+    ProgramTooLarge = 41,
+    IntegerOverflow = 42,
+    DivisionByZero = 43
+}
+
+const ALL_CODES: [ErrorCode; 35] = [
+    ErrorCode::UnknownConstant,
+    ErrorCode::LogicalOperandType,
+    ErrorCode::UnterminatedString,
+    ErrorCode::BinaryOperandType,
+    ErrorCode::NegateOperandType,
+    ErrorCode::NotOperandType,
+    ErrorCode::UnprintableValue,
+    ErrorCode::MissingSourceFile,
+    ErrorCode::UnreadableFile,
+    ErrorCode::UndefinedVariable,
+    ErrorCode::ExpectedVariableName,
+    ErrorCode::MissingSemicolon,
+    ErrorCode::UnclosedBlock,
+    ErrorCode::UnclosedGrouping,
+    ErrorCode::MisspelledKeyword,
+    ErrorCode::NatraWithoutYadi,
+    ErrorCode::PatakOutsideLoop,
+    ErrorCode::ExpectedBlock,
+    ErrorCode::ExpectedExpression,
+    ErrorCode::UnmatchedBrace,
+    ErrorCode::CorruptBytecode,
+    ErrorCode::UnsupportedBytecodeVersion,
+    ErrorCode::UnwritableFile,
+    ErrorCode::InvalidBytecode,
+    ErrorCode::UnknownOption,
+    ErrorCode::UndefinedFunction,
+    ErrorCode::WrongArgumentCount,
+    ErrorCode::NativeFunctionError,
+    ErrorCode::InvalidCall,
+    ErrorCode::LimitExceeded,
+    ErrorCode::Cancelled,
+    ErrorCode::CapabilityDenied,
+    ErrorCode::ProgramTooLarge,
+    ErrorCode::IntegerOverflow,
+    ErrorCode::DivisionByZero
+];
+
+impl ErrorCode {
+    #[inline]
+    pub fn number(self) -> i32 {
+        self as i32
+    }
+
+    /*
+    * Accepts "B0007", "b0007" or a bare "7".
+    */
+    pub fn parse(code: &str) -> Option<ErrorCode> {
+        let digits: &str = code.strip_prefix(['B', 'b']).unwrap_or(code);
+        let number: i32 = digits.parse().ok()?;
+        ALL_CODES.iter().copied().find(|c| c.number() == number)
+    }
+
+    pub fn explanation(self) -> &'static str {
+        match self {
+            ErrorCode::UnknownConstant => EXPLAIN_B0002,
+            ErrorCode::LogicalOperandType => EXPLAIN_B0003,
+            ErrorCode::UnterminatedString => EXPLAIN_B0004,
+            ErrorCode::BinaryOperandType => EXPLAIN_B0007,
+            ErrorCode::NegateOperandType => EXPLAIN_B0008,
+            ErrorCode::NotOperandType => EXPLAIN_B0009,
+            ErrorCode::UnprintableValue => EXPLAIN_B0010,
+            ErrorCode::MissingSourceFile => EXPLAIN_B0012,
+            ErrorCode::UnreadableFile => EXPLAIN_B0015,
+            ErrorCode::UndefinedVariable => EXPLAIN_B0018,
+            ErrorCode::ExpectedVariableName => EXPLAIN_B0019,
+            ErrorCode::MissingSemicolon => EXPLAIN_B0020,
+            ErrorCode::UnclosedBlock => EXPLAIN_B0021,
+            ErrorCode::UnclosedGrouping => EXPLAIN_B0022,
+            ErrorCode::MisspelledKeyword => EXPLAIN_B0023,
+            ErrorCode::NatraWithoutYadi => EXPLAIN_B0024,
+            ErrorCode::PatakOutsideLoop => EXPLAIN_B0025,
+            ErrorCode::ExpectedBlock => EXPLAIN_B0026,
+            ErrorCode::ExpectedExpression => EXPLAIN_B0027,
+            ErrorCode::UnmatchedBrace => EXPLAIN_B0028,
+            ErrorCode::CorruptBytecode => EXPLAIN_B0029,
+            ErrorCode::UnsupportedBytecodeVersion => EXPLAIN_B0030,
+            ErrorCode::UnwritableFile => EXPLAIN_B0031,
+            ErrorCode::InvalidBytecode => EXPLAIN_B0032,
+            ErrorCode::UnknownOption => EXPLAIN_B0033,
+            ErrorCode::UndefinedFunction => EXPLAIN_B0034,
+            ErrorCode::WrongArgumentCount => EXPLAIN_B0035,
+            ErrorCode::NativeFunctionError => EXPLAIN_B0036,
+            ErrorCode::InvalidCall => EXPLAIN_B0037,
+            ErrorCode::LimitExceeded => EXPLAIN_B0038,
+            ErrorCode::Cancelled => EXPLAIN_B0039,
+            ErrorCode::CapabilityDenied => EXPLAIN_B0040,
+            ErrorCode::ProgramTooLarge => EXPLAIN_B0041,
+            ErrorCode::IntegerOverflow => EXPLAIN_B0042,
+            ErrorCode::DivisionByZero => EXPLAIN_B0043
+        }
+    }
+
+    pub fn exit(self) -> ! {
+        std::process::exit(self.number());
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "B{:04}", self.number())
+    }
+}
+
+/*
+* Warnings are grouped into named lints so each one can be silenced with
+* `--allow <lint>` or turned into an error with `--deny <lint>`.
+*/
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Lint {
+    UnusedVariable,
+    UnreachableCode,
+    ConstantCondition,
+    ShadowedVariable,
+    UndefinedVariable
+}
+
+pub const ALL_LINTS: [Lint; 5] = [
+    Lint::UnusedVariable,
+    Lint::UnreachableCode,
+    Lint::ConstantCondition,
+    Lint::ShadowedVariable,
+    Lint::UndefinedVariable
+];
+
+impl Lint {
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::UnusedVariable => "unused_variable",
+            Lint::UnreachableCode => "unreachable_code",
+            Lint::ConstantCondition => "constant_condition",
+            Lint::ShadowedVariable => "shadowed_variable",
+            Lint::UndefinedVariable => "undefined_variable"
+        }
+    }
+
+    /*
+    * Accepts both "unused_variable" and "unused-variable".
+    */
+    pub fn parse(name: &str) -> Option<Lint> {
+        let name: String = name.replace('-', "_");
+        ALL_LINTS.iter().copied().find(|lint| lint.name() == name)
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny
+}
+
+#[derive(Clone, Debug)]
+pub struct LintConfig {
+    levels: HashMap<Lint, LintLevel>
+}
+
+impl LintConfig {
+    pub fn new() -> LintConfig {
+        LintConfig {
+            levels: HashMap::from_iter(ALL_LINTS.iter().map(|lint| (*lint, LintLevel::Warn)))
+        }
+    }
+
+    #[inline]
+    pub fn level(&self, lint: Lint) -> LintLevel {
+        *self.levels.get(&lint).unwrap_or(&LintLevel::Warn)
+    }
+
+    #[inline]
+    pub fn set(&mut self, lint: Lint, level: LintLevel) {
+        self.levels.insert(lint, level);
+    }
+
+    pub fn set_all(&mut self, level: LintLevel) {
+        for lint in ALL_LINTS {
+            self.set(lint, level);
+        }
+    }
+
+    /*
+    * Applies one `--allow`/`--warn`/`--deny` flag. `name` is either a lint
+    * name or "warnings", which stands for every lint. Returns false when the
+    * name is not recognised.
+    */
+    pub fn apply(&mut self, name: &str, level: LintLevel) -> bool {
+        if name == "warnings" {
+            self.set_all(level);
+            return true;
+        }
+        match Lint::parse(name) {
+            Some(lint) => {
+                self.set(lint, level);
+                true
+            },
+            None => false
+        }
+    }
+}
+
+/*
+* Prints "<kind>[Bxxxx]: <message>" to stderr followed by a pointer to
+* `bish --explain`.
+*/
+pub fn report(code: ErrorCode, kind: &str, message: &str) {
+    eprintln!("{}[{}]: {}", kind, code, message);
+    eprintln!("{}", explain_hint(code));
+}
+
+/*
+* Like report, then exits with the code's number.
+*/
+pub fn fatal(code: ErrorCode, kind: &str, message: &str) -> ! {
+    report(code, kind, message);
+    code.exit();
+}
+
+/*
+* An error that stopped a program: it could not be read or loaded, or it
+* failed while running. Running a file reports it and exits with its code;
+* the REPL reports it and carries on.
+*/
+#[derive(Clone, Debug)]
+pub struct RuntimeError {
+    pub code: ErrorCode,
+    pub kind: &'static str,
+    pub message: String
+}
+
+impl RuntimeError {
+    pub fn new(code: ErrorCode, kind: &'static str, message: String) -> RuntimeError {
+        RuntimeError {
+            code,
+            kind,
+            message
+        }
+    }
+
+    /*
+    * An error raised by a native function, with a message for the user.
+    */
+    pub fn native(message: impl fmt::Display) -> RuntimeError {
+        RuntimeError::new(ErrorCode::NativeFunctionError, "Runtime error", message.to_string())
+    }
+
+    pub fn report(&self) {
+        report(self.code, self.kind, &self.message);
+    }
+}
+
+/*
+* Lets native functions use `?` on any Rust error. RuntimeError must not
+* implement std::error::Error itself, or this would overlap with From<T> for T.
+*/
+impl<E: std::error::Error> From<E> for RuntimeError {
+    fn from(error: E) -> RuntimeError {
+        RuntimeError::native(error)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Severity {
+    Error,
+    Warning
+}
+
+/*
+* Prints a diagnostic that points at a place in the source:
+*
+*   Compilation error[B0020]: <message>
+*     --> file.bish:3:14
+*     = <note>
+*
+* Colours are only used when stderr is a terminal and NO_COLOR is unset,
+* so redirected output stays plain text.
+*/
+pub fn print_located(severity: Severity, title: &str, message: &str, location: &str, note: &str) {
+    let (title_color, arrow_color, reset): (&str, &str, &str) = if use_color() {
+        let title_color: &str = match severity {
+            Severity::Error => "\x1b[1;31m",
+            Severity::Warning => "\x1b[1;33m"
+        };
+        (title_color, "\x1b[1;34m", "\x1b[0m")
+    }
+    else {
+        ("", "", "")
+    };
+    eprintln!("{}{}{}: {}", title_color, title, reset, message);
+    eprintln!("  {}-->{} {}", arrow_color, reset, location);
+    eprintln!("  {}={} {}", arrow_color, reset, note);
+    eprintln!();
+}
+
+fn use_color() -> bool {
+    std::env::var_os("NO_COLOR").is_none() && std::io::stderr().is_terminal()
+}
+
+#[inline]
+pub fn explain_hint(code: ErrorCode) -> String {
+    format!("Yo error barema thap jankari: `bish --explain {}`", code)
+}
+
+#[inline]
+pub fn did_you_mean(name: &str) -> String {
+    format!("'{}' bhanna khojnu bhayeko ho?", name)
+}
+
+/*
+* Returns the candidate closest to `name`, provided it is close enough to be
+* a plausible typo: one edit for short names, roughly one edit per three
+* characters for longer ones. Ties go to the candidate seen first.
+*/
+pub fn suggest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let max_distance: usize = std::cmp::max(1, name.chars().count() / 3);
+    let mut best: Option<(&str, usize)> = None;
+    for candidate in candidates {
+        if candidate == name {
+            continue;
+        }
+        let distance: usize = edit_distance(name, candidate);
+        if distance > max_distance {
+            continue;
+        }
+        match best {
+            Some((_, best_distance)) if best_distance <= distance => (),
+            _ => best = Some((candidate, distance))
+        }
+    }
+    best.map(|(candidate, _)| candidate)
+}
+
+/*
+* Optimal string alignment distance: Levenshtein plus transposition of two
+* adjacent characters, which is the most common typing mistake.
+*/
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut table: Vec<Vec<usize>> = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in table.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in table[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost: usize = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut distance: usize = (table[i - 1][j] + 1)
+                .min(table[i][j - 1] + 1)
+                .min(table[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(table[i - 2][j - 2] + 1);
+            }
+            table[i][j] = distance;
+        }
+    }
+    table[a.len()][b.len()]
+}
+
+const EXPLAIN_B0002: &str = "\
+A constant in the chunk's constant pool has no known type.
+
+The compiler only ever writes integers, floats, booleans, strings and nil
+into the constant pool, so this error means the bytecode was built or
+modified by something other than the bish compiler. It is an internal error;
+please report it together with the program that triggered it.
+";
+
+const EXPLAIN_B0003: &str = "\
+The operands of `&` or `|` are not both integers.
+
+`&` (ra) and `|` (wa) work bit by bit on whole numbers only.
+
+Erroneous example:
+
+    dekhau 1.5 & 3;
+    dekhau sahi | 2;
+
+Both sides must be `int` values. This prints 2 and then 5:
+
+    dekhau 6 & 3;
+    dekhau 4 | 1;
+";
+
+const EXPLAIN_B0004: &str = "\
+A string literal was opened with `\"` but never closed.
+
+Erroneous example:
+
+    dekhau \"namaste;
+
+Every string needs a closing quote:
+
+    dekhau \"namaste\";
+";
+
+const EXPLAIN_B0007: &str = "\
+A binary operator was applied to values whose types it does not support.
+
+Comparison operators only accept matching types. `thulo` and `sano` compare
+two ints or two floats; `barabar` compares two values of the same type.
+
+Erroneous example:
+
+    dekhau 5 barabar \"5\";
+    dekhau sahi thulo galat;
+
+Compare values of the same type instead:
+
+    dekhau 5 barabar 5;
+    dekhau 2.5 thulo 1.0;
+";
+
+const EXPLAIN_B0008: &str = "\
+The negate operator `-` was applied to something that is not a number.
+
+Erroneous example:
+
+    dekhau -sahi;
+    dekhau -\"das\";
+
+Only ints and floats can be negated:
+
+    dekhau -10;
+    dekhau -2.5;
+";
+
+const EXPLAIN_B0009: &str = "\
+The `chhaina` operator was applied to a value that is neither a bool nor
+an int.
+
+Erroneous example:
+
+    dekhau chhaina \"ho\";
+
+`chhaina` flips a bool, and treats the int `0` as false and any other int
+as true. This prints galat and then sahi:
+
+    dekhau chhaina sahi;
+    dekhau chhaina 0;
+";
+
+const EXPLAIN_B0010: &str = "\
+`dekhau` was given a value it does not know how to print.
+
+Every value a bish program can produce is printable, so this usually means
+the stack was empty when `dekhau` ran. It is an internal error; please report
+it together with the program that triggered it.
+";
+
+const EXPLAIN_B0012: &str = "\
+bish was given options but no source file to work on.
+
+Pass the path of a `.bish` file after the options:
+
+    $ bish --deny warnings demos/program.bish
+
+Running `bish` with no arguments at all starts the interactive prompt.
+";
+
+const EXPLAIN_B0015: &str = "\
+The source file given on the command line could not be read.
+
+Check that the path is spelled correctly, that the file exists and that
+you have permission to read it.
+";
+
+const EXPLAIN_B0018: &str = "\
+A variable was used before it was created with `rakha`.
+
+Erroneous example:
+
+    dekhau umer;
+    rakha umer ma 20;
+
+Create the variable first, then use it:
+
+    rakha umer ma 20;
+    dekhau umer;
+
+If the name is close to a variable that does exist, or to a keyword, the
+error suggests it. `dekhau umre;` after `rakha umer ma 20;` suggests `umer`.
+When the close name is already known while compiling, the
+`undefined_variable` warning suggests it before the program runs.
+";
+
+const EXPLAIN_B0019: &str = "\
+`rakha` must be followed by the name of the variable being created.
+
+Erroneous example:
+
+    rakha ma 5;
+    rakha 10 ma 5;
+
+Give the variable a name made of letters, digits and `_`, starting with a
+letter or `_`:
+
+    rakha x ma 5;
+";
+
+const EXPLAIN_B0020: &str = "\
+A statement was not terminated with `;`.
+
+Erroneous example:
+
+    rakha x ma 5
+    dekhau x
+
+Every statement ends with a semicolon:
+
+    rakha x ma 5;
+    dekhau x;
+";
+
+const EXPLAIN_B0021: &str = "\
+A block opened with `{` was never closed with `}`.
+
+Erroneous example:
+
+    yadi x barabar 5 {
+        dekhau x;
+
+Close every block:
+
+    yadi x barabar 5 {
+        dekhau x;
+    }
+";
+
+const EXPLAIN_B0022: &str = "\
+A parenthesised expression opened with `(` was never closed with `)`.
+
+Erroneous example:
+
+    dekhau (1 + 2;
+
+Close the grouping before ending the statement:
+
+    dekhau (1 + 2);
+";
+
+const EXPLAIN_B0023: &str = "\
+A statement starts with a name that looks like a misspelled keyword.
+
+Erroneous example:
+
+    dekhao \"namaste\";
+    rakhaa x ma 5;
+
+bish read `dekhao` as a variable and then found another value where it
+expected `;`. Spell the keyword correctly:
+
+    dekhau \"namaste\";
+    rakha x ma 5;
+";
+
+const EXPLAIN_B0024: &str = "\
+`natra` was used without a `yadi` block in front of it.
+
+Erroneous example:
+
+    dekhau 1;
+    natra {
+        dekhau 2;
+    }
+
+`natra` must directly follow the closing `}` of a `yadi` block:
+
+    yadi x thulo 5 {
+        dekhau 1;
+    } natra {
+        dekhau 2;
+    }
+";
+
+const EXPLAIN_B0025: &str = "\
+`patak` was used outside of a `ghumau` loop.
+
+Erroneous example:
+
+    rakha x ma 1;
+    patak;
+
+`patak` leaves the innermost loop, so it only makes sense inside one:
+
+    rakha x ma 0;
+    ghumau sahi {
+        x ma x + 1;
+        yadi x barabar 10 {
+            patak;
+        }
+    }
+";
+
+const EXPLAIN_B0026: &str = "\
+The condition of a `yadi` or `ghumau` was not followed by a block.
+
+Erroneous example:
+
+    yadi x thulo 5 dekhau x;
+
+The body must be wrapped in braces:
+
+    yadi x thulo 5 {
+        dekhau x;
+    }
+";
+
+const EXPLAIN_B0027: &str = "\
+A value was expected, but the program continued with something that cannot
+start an expression.
+
+Erroneous example:
+
+    dekhau;
+    rakha x ma 1 +;
+
+Give the missing value:
+
+    dekhau \"namaste\";
+    rakha x ma 1 + 2;
+";
+
+const EXPLAIN_B0028: &str = "\
+A `}` was found that does not close any block.
+
+Erroneous example:
+
+    dekhau 1;
+    }
+
+Remove the extra brace, or add the `{` that was meant to open the block.
+";
+
+const EXPLAIN_B0029: &str = "\
+A `.bishc` file passed to `bish run` is not valid compiled bish code.
+
+The file is either not a `.bishc` file at all, was cut short, or was
+changed after `bish build` wrote it; every `.bishc` file carries a checksum
+that is verified before anything is run. Build it again from the source:
+
+    $ bish build program.bish -o program.bishc
+";
+
+const EXPLAIN_B0030: &str = "\
+A `.bishc` file was written by a version of bish whose bytecode format
+this version does not understand.
+
+Compiled files are not portable between bytecode format versions. Build
+the file again with the bish that is going to run it:
+
+    $ bish build program.bish -o program.bishc
+";
+
+const EXPLAIN_B0031: &str = "\
+`bish build` could not write the compiled `.bishc` file, `bish fmt` could
+not write a formatted file back, or the output of `dekhau` could not be
+written, for example because the program reading it has stopped.
+
+Check that the directory given with `-o` exists and that you have
+permission to write to it.
+";
+
+const EXPLAIN_B0032: &str = "\
+The bytecode about to run failed verification, so bish refused to run it.
+
+Before running anything bish checks that every instruction is a known
+opcode, that constants and jump targets it refers to exist, and that no
+instruction takes more values off the stack than are on it. The error names
+the offset of the offending instruction and, when known, its source line.
+
+Bytecode produced by `bish build` always passes, so this means a `.bishc`
+file was written by hand or by another tool, or points to a bug in the
+compiler; please report it together with the program that triggered it.
+";
+
+const EXPLAIN_B0033: &str = "\
+The command line had an option bish does not know, an option the chosen
+subcommand does not take, or an option missing the value that goes after it
+or given one it does not accept, such as `--explain` with no error code.
+
+Erroneous example:
+
+    $ bish build --trace demos/program.bish
+
+`--trace` only makes sense while a program runs, so only `run` takes it:
+
+    $ bish run --trace demos/program.bish
+
+Options go between the subcommand and the program. For `run`, everything
+after the program is passed to the program itself instead. Running
+`bish --help` lists the options each subcommand takes.
+";
+
+const EXPLAIN_B0034: &str = "\
+A function was called that the program running it does not provide.
+
+bish code can only call functions the program embedding bish has
+registered; the language itself has no way to define them. Erroneous
+example, when nothing named `jod` is registered:
+
+    dekhau jod(1, 2);
+
+Check the spelling against the functions your host program registers. The
+error suggests a registered function with a similar name if there is one.
+";
+
+const EXPLAIN_B0035: &str = "\
+A function was called with a different number of arguments than it takes.
+
+Erroneous example, for a `jod` registered to take two arguments:
+
+    dekhau jod(1, 2, 3);
+
+The error says how many arguments the function takes.
+";
+
+const EXPLAIN_B0036: &str = "\
+A function provided by the program embedding bish failed. The message
+after the function's name comes from that function, so check the arguments
+passed to it and the documentation of the program providing it.
+";
+
+const EXPLAIN_B0037: &str = "\
+Something other than a name was called like a function, a call was given
+more than 255 arguments, or a name holding a value that is not a function
+was called.
+
+Erroneous example:
+
+    dekhau (jod)(1, 2);
+
+Only a function's name can be called:
+
+    dekhau jod(1, 2);
+
+A variable hides a native function with the same name, so after
+`rakha samay ma 1;` the call `samay()` tries to call the number 1. Pick
+another name for the variable.
+";
+
+const EXPLAIN_B0038: &str = "\
+The program used more of something than it was allowed to, and was stopped.
+
+The limits are:
+
+    instructions   instructions run, which catches loops that never end
+    stack          values on the stack at once (256 unless changed)
+    call_depth     native functions running inside one another (64)
+    heap_bytes     bytes of strings, arrays and maps held by variables and
+                   the stack
+
+Only the stack and call depth are limited unless the program running bish
+says otherwise. On the command line, `--limit <name>=<number>` sets a limit
+and `--limit <name>=none` removes one:
+
+    $ bish run --limit instructions=1000000 program.bish
+
+If the program is meant to do this much work, raise the limit; otherwise
+look for a `ghumau` loop whose condition never becomes `galat`.
+";
+
+const EXPLAIN_B0039: &str = "\
+The program was stopped before it finished, either because the program
+running bish asked for it to stop or because it ran for longer than its
+timeout.
+
+A host embedding bish stops a program from another thread through the
+handle `Vm::interrupt_handle` returns, and sets a timeout with
+`vm.limits.timeout`. On the command line the timeout is given in
+milliseconds:
+
+    $ bish run --limit timeout_ms=2000 program.bish
+
+Whatever the program printed or stored before it was stopped stays as it
+was. If it was meant to run this long, give it a longer timeout; otherwise
+look for a `ghumau` loop whose condition never becomes `galat`.
+";
+
+const EXPLAIN_B0040: &str = "\
+The program called a built-in function from a group it has not been
+allowed to use.
+
+Functions that reach outside bish come in groups, and a program starts out
+with none of them, so it can only compute and print:
+
+    fs        padh(path), lekh(path, text)
+    env       vatavaran(name)
+    clock     samay()
+    random    jathabhavi()
+    process   chalau(command)
+
+Erroneous example, run without `--grant fs`:
+
+    dekhau padh(\"notes.txt\");
+
+On the command line `--grant <group>` allows one group and `--grant all`
+allows every one of them:
+
+    $ bish run --grant fs program.bish
+
+A program embedding bish grants groups through `vm.capabilities`.
+";
+
+const EXPLAIN_B0041: &str = "\
+The program is too big for bish's bytecode. Instructions name constants and
+jump over blocks with 16-bit numbers, so a program can hold at most 65536
+distinct constants and names, and the code inside one `yadi`, `natra` or
+`ghumau` block can be at most 65535 bytes long.
+
+Erroneous example:
+
+    yadi x {
+        dekhau 1;
+        dekhau 2;
+        ... twenty thousand more lines ...
+    }
+
+Split a long block into several smaller ones, or a huge program into
+several files.
+";
+
+const EXPLAIN_B0042: &str = "\
+An integer went outside the range a 64-bit integer can hold, from
+-9223372036854775808 to 9223372036854775807. This happens when `+`, `-` or
+`*` on two integers, or `-` on one, gives a result outside that range, or
+when an integer literal in the program is itself too big.
+
+Erroneous example:
+
+    rakha x ma 9223372036854775807;
+    dekhau x + 1;
+
+Use a float such as `9223372036854775807.0` when the exact value does not
+matter.
+";
+
+const EXPLAIN_B0043: &str = "\
+An integer was divided by zero. Integer division has no answer for a zero
+divisor, so the program stops. Dividing a float by zero is allowed and
+gives infinity or NaN.
+
+Erroneous example:
+
+    rakha x ma 0;
+    dekhau 10 / x;
+
+Check the divisor first:
+
+    yadi chhaina (x barabar 0) {
+        dekhau 10 / x;
+    }
+";
@@ -0,0 +1,154 @@
+// MIT License
+//
+// Copyright (c) 2023 Ramesh Poudel
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+use crate::ast::{BinaryOp, Expr, Literal, Stmt, UnaryOp};
+
+const INDENT: &str = "    ";
+
+/*
+* Prints a parsed program back as source in the one layout `bish fmt` uses:
+* four spaces per block, one statement per line and single spaces around
+* operators. Parentheses are kept exactly where the source had them, and a
+* blank line between two statements in the source is kept as one.
+*/
+pub fn format(program: &[Stmt]) -> String {
+    let mut out: String = String::new();
+    _format_body(&mut out, program, 0);
+    out
+}
+
+fn _format_body(out: &mut String, body: &[Stmt], depth: usize) {
+    for (idx, stmt) in body.iter().enumerate() {
+        if idx > 0 && stmt.span().line > _last_line(&body[idx - 1]) + 1 {
+            out.push('\n');
+        }
+        out.push_str(&INDENT.repeat(depth));
+        _format_stmt(out, stmt, depth);
+        out.push('\n');
+    }
+}
+
+fn _format_stmt(out: &mut String, stmt: &Stmt, depth: usize) {
+    match stmt {
+        Stmt::Print { value, .. } => out.push_str(&format!("dekhau {};", _format_expr(value))),
+        Stmt::Expression { expr, .. } => out.push_str(&format!("{};", _format_expr(expr))),
+        Stmt::VarDecl { name, initializer: Some(value), .. } => out.push_str(&format!("rakha {} ma {};", name, _format_expr(value))),
+        Stmt::VarDecl { name, initializer: None, .. } => out.push_str(&format!("rakha {};", name)),
+        Stmt::Assign { name, value, .. } => out.push_str(&format!("{} ma {};", name, _format_expr(value))),
+        Stmt::If { condition, then_branch, else_branch, .. } => {
+            out.push_str(&format!("yadi {} ", _format_expr(condition)));
+            _format_block(out, then_branch, depth);
+            match else_branch.as_deref() {
+                Some(Stmt::Block { body, .. }) => {
+                    out.push_str(" natra ");
+                    _format_block(out, body, depth);
+                },
+                Some(elif) => {
+                    out.push_str(" natra ");
+                    _format_stmt(out, elif, depth);
+                },
+                None => ()
+            }
+        },
+        Stmt::While { condition, body, .. } => {
+            out.push_str(&format!("ghumau {} ", _format_expr(condition)));
+            _format_block(out, body, depth);
+        },
+        Stmt::Break { .. } => out.push_str("patak;"),
+        Stmt::Block { body, .. } => _format_block(out, body, depth)
+    }
+}
+
+fn _format_block(out: &mut String, body: &[Stmt], depth: usize) {
+    out.push_str("{\n");
+    _format_body(out, body, depth + 1);
+    out.push_str(&INDENT.repeat(depth));
+    out.push('}');
+}
+
+/*
+* In plain decimal with a '.', the only way the scanner reads a float: Debug
+* would write 1e20 and 1e-7. Display never uses an exponent and prints the
+* shortest digits that read back as the same value.
+*/
+fn _format_double(value: f64) -> String {
+    let text: String = value.to_string();
+    if text.contains('.') {
+        text
+    }
+    else {
+        format!("{}.0", text)
+    }
+}
+
+fn _format_expr(expr: &Expr) -> String {
+    match expr {
+        Expr::Literal { value, .. } => match value {
+            Literal::Integer(value) => value.to_string(),
+            Literal::Double(value) => _format_double(*value),
+            Literal::Boolean(value) => String::from(if *value { "sahi" } else { "galat" }),
+            Literal::CString(value) => format!("\"{}\"", value),
+            Literal::Nil => String::from("nil")
+        },
+        Expr::Variable { name, .. } => name.clone(),
+        Expr::Grouping { inner, .. } => format!("({})", _format_expr(inner)),
+        Expr::Unary { op: UnaryOp::Negate, operand, .. } => {
+            let operand: String = _format_expr(operand);
+            // `--x` would still parse, but reads like a decrement.
+            if operand.starts_with('-') { format!("- {}", operand) } else { format!("-{}", operand) }
+        },
+        Expr::Unary { op: UnaryOp::Not, operand, .. } => format!("chhaina {}", _format_expr(operand)),
+        Expr::Binary { op, left, right, .. } => format!("{} {} {}", _format_expr(left), _operator(*op), _format_expr(right)),
+        Expr::Call { callee, args, .. } => format!("{}({})", callee, args.iter().map(_format_expr).collect::<Vec<String>>().join(", "))
+    }
+}
+
+fn _operator(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "+",
+        BinaryOp::Subtract => "-",
+        BinaryOp::Multiply => "*",
+        BinaryOp::Divide => "/",
+        BinaryOp::Greater => "thulo",
+        BinaryOp::Less => "sano",
+        BinaryOp::Equal => "barabar",
+        BinaryOp::And => "&",
+        BinaryOp::Or => "|"
+    }
+}
+
+/*
+* The last source line a statement is known to reach. Spans only record where
+* a node starts, so a block is assumed to end with its '}' on a line of its own.
+*/
+fn _last_line(stmt: &Stmt) -> usize {
+    match stmt {
+        Stmt::If { then_branch, else_branch, span, .. } => {
+            let then_end: usize = then_branch.last().map_or(span.line, _last_line) + 1;
+            else_branch.as_deref().map_or(then_end, |branch| _last_line(branch).max(then_end))
+        },
+        Stmt::While { body, span, .. } |
+        Stmt::Block { body, span } => body.last().map_or(span.line, _last_line) + 1,
+        _ => stmt.span().line
+    }
+}
@@ -0,0 +1,253 @@
+// MIT License
+//
+// Copyright (c) 2023 Ramesh Poudel
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+#![allow(clippy::new_without_default)]
+
+use std::cell::RefCell;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::mem;
+use std::rc::{Rc, Weak};
+
+use crate::chunk::Value;
+
+// The fewest tracked objects at which an allocation collects, and how much
+// the heap may grow past what survived the last collection before the next.
+pub const GC_MIN_OBJECTS: usize = 1024;
+pub const GC_GROWTH_FACTOR: usize = 2;
+
+type ArrayCell = RefCell<Vec<Value>>;
+type MapCell = RefCell<HashMap<Rc<str>, Value>>;
+
+enum Object {
+    Array(Weak<ArrayCell>),
+    Map(Weak<MapCell>)
+}
+
+// An object held on to for the length of one collection.
+enum Live {
+    Array(Rc<ArrayCell>),
+    Map(Rc<MapCell>)
+}
+
+impl Live {
+    fn of(value: &Value) -> Option<Live> {
+        match value {
+            Value::Array(array) => Some(Live::Array(array.clone())),
+            Value::Map(map) => Some(Live::Map(map.clone())),
+            _ => None
+        }
+    }
+
+    fn address(&self) -> *const () {
+        match self {
+            Live::Array(array) => Rc::as_ptr(array) as *const (),
+            Live::Map(map) => Rc::as_ptr(map) as *const ()
+        }
+    }
+
+    fn strong_count(&self) -> usize {
+        match self {
+            Live::Array(array) => Rc::strong_count(array),
+            Live::Map(map) => Rc::strong_count(map)
+        }
+    }
+
+    /*
+    * Calls `visit` on every value the object holds. Returns false without
+    * visiting anything if the object is borrowed mutably, as it is while a
+    * native function is changing it.
+    */
+    fn children(&self, mut visit: impl FnMut(&Value)) -> bool {
+        match self {
+            Live::Array(array) => match array.try_borrow() {
+                Ok(values) => values.iter().for_each(visit),
+                Err(_) => return false
+            },
+            Live::Map(map) => match map.try_borrow() {
+                Ok(entries) => entries.values().for_each(visit),
+                Err(_) => return false
+            }
+        }
+        true
+    }
+
+    /*
+    * Empties the object, dropping its references to other objects so that a
+    * cycle it is part of can be freed.
+    */
+    fn clear(&self) {
+        let dropped = match self {
+            Live::Array(array) => array.try_borrow_mut().map(|mut values| Value::array(mem::take(&mut *values))),
+            Live::Map(map) => map.try_borrow_mut().map(|mut entries| Value::map(mem::take(&mut *entries)))
+        };
+        drop(dropped);
+    }
+
+    fn downgrade(&self) -> Object {
+        match self {
+            Live::Array(array) => Object::Array(Rc::downgrade(array)),
+            Live::Map(map) => Object::Map(Rc::downgrade(map))
+        }
+    }
+}
+
+/*
+* The arrays and maps a Vm has allocated. They are reference counted, which
+* frees everything except cycles; a collection finds the objects that are
+* only reachable from each other and empties them, which breaks the cycles
+* and lets the counts free them.
+*
+* An object is a root if something outside the heap refers to it, which
+* covers the stack, globals and any Value a host or native function is
+* holding in Rust. Those references are found by comparing each object's
+* reference count with the references other objects hold to it. Objects are
+* marked from the roots and the rest are swept.
+*
+* Strings and functions cannot refer to other values, so they are left to
+* their reference counts.
+*/
+pub struct Heap {
+    objects: Vec<Object>,
+    // An allocation that finds this many objects tracked collects first.
+    next_gc: usize,
+    pub growth_factor: usize,
+    // Collect on every allocation, to shake out objects that are used after
+    // they should have been marked.
+    pub stress: bool,
+    pub collections: usize
+}
+
+impl Heap {
+    pub fn new() -> Heap {
+        Heap {
+            objects: Vec::new(),
+            next_gc: GC_MIN_OBJECTS,
+            growth_factor: GC_GROWTH_FACTOR,
+            stress: false,
+            collections: 0
+        }
+    }
+
+    /*
+    * How many objects are tracked; some may have been freed by their
+    * reference counts since the last collection.
+    */
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    pub(crate) fn should_collect(&self) -> bool {
+        self.stress || self.objects.len() >= self.next_gc
+    }
+
+    pub(crate) fn track(&mut self, value: &Value) {
+        if let Some(object) = Live::of(value) {
+            self.objects.push(object.downgrade());
+        }
+    }
+
+    /*
+    * Frees every tracked object that cannot be reached from outside the
+    * heap and returns how many there were. Containers made outside the Vm,
+    * with Value::array or Value::map, are picked up here once `roots` or a
+    * tracked object refers to them.
+    */
+    pub(crate) fn collect<'a>(&mut self, roots: impl Iterator<Item = &'a Value>) -> usize {
+        self.collections += 1;
+        let mut live: Vec<Live> = Vec::new();
+        let mut index: HashMap<*const (), usize> = HashMap::new();
+        let mut add = |object: Live, live: &mut Vec<Live>| {
+            if let Entry::Vacant(slot) = index.entry(object.address()) {
+                slot.insert(live.len());
+                live.push(object);
+            }
+        };
+        for object in self.objects.drain(..) {
+            let object: Option<Live> = match object {
+                Object::Array(array) => array.upgrade().map(Live::Array),
+                Object::Map(map) => map.upgrade().map(Live::Map)
+            };
+            if let Some(object) = object {
+                add(object, &mut live);
+            }
+        }
+        for value in roots {
+            if let Some(object) = Live::of(value) {
+                add(object, &mut live);
+            }
+        }
+        let mut idx: usize = 0;
+        while idx < live.len() {
+            let mut found: Vec<Live> = Vec::new();
+            live[idx].children(|value| found.extend(Live::of(value)));
+            for object in found {
+                add(object, &mut live);
+            }
+            idx += 1;
+        }
+
+        // References from outside the heap: the count, less the one `live`
+        // holds, less the ones from other objects.
+        let mut outside: Vec<isize> = live.iter().map(|object| object.strong_count() as isize - 1).collect();
+        let mut pinned: Vec<bool> = vec![false; live.len()];
+        for (idx, object) in live.iter().enumerate() {
+            pinned[idx] = !object.children(|value| {
+                if let Some(child) = Live::of(value) {
+                    outside[index[&child.address()]] -= 1;
+                }
+            });
+        }
+
+        let mut marked: Vec<bool> = vec![false; live.len()];
+        let mut worklist: Vec<usize> = (0..live.len()).filter(|idx| outside[*idx] > 0 || pinned[*idx]).collect();
+        while let Some(idx) = worklist.pop() {
+            if marked[idx] {
+                continue;
+            }
+            marked[idx] = true;
+            live[idx].children(|value| {
+                if let Some(child) = Live::of(value) {
+                    worklist.push(index[&child.address()]);
+                }
+            });
+        }
+
+        let mut freed: usize = 0;
+        for (idx, object) in live.iter().enumerate() {
+            if marked[idx] {
+                self.objects.push(object.downgrade());
+            }
+            else {
+                object.clear();
+                freed += 1;
+            }
+        }
+        self.next_gc = (self.objects.len() * self.growth_factor).max(GC_MIN_OBJECTS);
+        freed
+    }
+}
@@ -0,0 +1,115 @@
+// MIT License
+//
+// Copyright (c) 2023 Ramesh Poudel
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+#![allow(clippy::new_without_default)]
+
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+/*
+* A small number standing for a name or string. Two symbols from the same
+* Interner are equal exactly when their text is.
+*/
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    #[inline]
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/*
+* Hands out one Symbol, and one shared copy of the text, per distinct
+* string. A Vm interns every identifier and string constant of the code it
+* runs, so globals live in slots numbered by symbol and equal constant
+* strings share an allocation. Strings built while a program runs, such as
+* what a native returns, are not interned.
+*/
+pub struct Interner {
+    symbols: HashMap<String, Symbol>,
+    names: Vec<Rc<String>>,
+    // Where each entry of `names` lives, for is_interned. The Interner keeps
+    // every name alive, so an address is never reused for other text.
+    addresses: HashSet<*const String>
+}
+
+impl Interner {
+    pub fn new() -> Interner {
+        Interner {
+            symbols: HashMap::new(),
+            names: Vec::new(),
+            addresses: HashSet::new()
+        }
+    }
+
+    pub fn intern(&mut self, text: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(text) {
+            return *symbol;
+        }
+        let symbol: Symbol = Symbol(self.names.len() as u32);
+        let name: Rc<String> = Rc::new(text.to_owned());
+        self.addresses.insert(Rc::as_ptr(&name));
+        self.names.push(name);
+        self.symbols.insert(text.to_owned(), symbol);
+        symbol
+    }
+
+    /*
+    * The symbol for `text` if it has been interned, without interning it.
+    */
+    pub fn get(&self, text: &str) -> Option<Symbol> {
+        self.symbols.get(text).copied()
+    }
+
+    /*
+    * Whether `text` is the copy this Interner handed out, without looking
+    * at its contents. Two interned strings are equal only if they are the
+    * same copy.
+    */
+    #[inline]
+    pub fn is_interned(&self, text: &Rc<String>) -> bool {
+        self.addresses.contains(&Rc::as_ptr(text))
+    }
+
+    #[inline]
+    pub fn name(&self, symbol: Symbol) -> &Rc<String> {
+        &self.names[symbol.index()]
+    }
+
+    /*
+    * Every interned string, in Symbol order.
+    */
+    pub fn names(&self) -> &[Rc<String>] {
+        &self.names
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
@@ -0,0 +1,60 @@
+// MIT License
+//
+// Copyright (c) 2023 Ramesh Poudel
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+/*
+* bish as a library: compile and run bish code from Rust through vm::Vm, or
+* use the pieces of the pipeline on their own. Source goes through
+* scanner::Scanner, compiler::Parser, lint::Linter, optimizer and
+* codegen::CodeGen into a chunk::Chunk of bytecode, which bytecode saves to
+* and loads from .bishc files and verifier checks before the Vm runs it.
+*/
+
+#![allow(dead_code)]
+#![allow(unused)]
+#![allow(non_camel_case_types)]
+
+pub mod scanner;
+pub mod ast;
+pub mod compiler;
+pub mod lint;
+pub mod codegen;
+pub mod optimizer;
+pub mod chunk;
+pub mod bytecode;
+pub mod verifier;
+pub mod vm;
+pub mod limits;
+pub mod gc;
+pub mod intern;
+pub mod stdlib;
+pub mod repl;
+pub mod formatter;
+pub mod diagnostics;
+
+pub use chunk::{Chunk, Value};
+pub use scanner::Scanner;
+pub use diagnostics::RuntimeError;
+pub use gc::Heap;
+pub use limits::{InterruptHandle, Limits};
+pub use stdlib::{Capabilities, Capability};
+pub use vm::{Error, NativeFn, Vm};
@@ -0,0 +1,139 @@
+// MIT License
+//
+// Copyright (c) 2023 Ramesh Poudel
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+#![allow(clippy::new_without_default)]
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+// How many instructions run between looks at the clock, the interrupt flag
+// and the size of the heap.
+pub const CHECK_INTERVAL: u64 = 1024;
+
+// What Limits::new allows by default.
+pub const STACK_MAX: usize = 256;
+pub const CALL_DEPTH_MAX: usize = 64;
+
+/*
+* Caps on what one run of a program may use, for running code that is not
+* trusted. None means no cap. Going over any of them stops the program with
+* ErrorCode::LimitExceeded.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    // Instructions executed by one Vm::run.
+    pub instructions: Option<u64>,
+    // Values on the stack at the same time.
+    pub stack: Option<usize>,
+    // Native function calls running inside one another. bish code can only
+    // call natives, so this is reached when natives run bish code that
+    // calls natives in turn.
+    pub call_depth: Option<usize>,
+    // Bytes of heap data reachable from globals and the stack; see
+    // Vm::heap_bytes. Measured every CHECK_INTERVAL instructions and when
+    // the program ends.
+    pub heap_bytes: Option<usize>,
+    // Wall-clock time one Vm::run may take.
+    pub timeout: Option<Duration>
+}
+
+impl Limits {
+    /*
+    * The stack and call depth are capped; how long a program runs and how
+    * much it stores are not.
+    */
+    pub fn new() -> Limits {
+        Limits {
+            instructions: None,
+            stack: Some(STACK_MAX),
+            call_depth: Some(CALL_DEPTH_MAX),
+            heap_bytes: None,
+            timeout: None
+        }
+    }
+
+    pub fn unlimited() -> Limits {
+        Limits {
+            instructions: None,
+            stack: None,
+            call_depth: None,
+            heap_bytes: None,
+            timeout: None
+        }
+    }
+
+    /*
+    * Applies one `--limit <name>=<value>` flag, where the value is a number
+    * or "none", and the timeout is given as `timeout_ms`. Returns false when the name or the value is not recognised.
+    */
+    pub fn apply(&mut self, setting: &str) -> bool {
+        let (name, value): (&str, &str) = match setting.split_once('=') {
+            Some(pair) => pair,
+            None => return false
+        };
+        let value: Option<u64> = match value.trim() {
+            "none" => None,
+            number => match number.parse() {
+                Ok(number) => Some(number),
+                Err(_) => return false
+            }
+        };
+        match name.trim() {
+            "instructions" => self.instructions = value,
+            "stack" => self.stack = value.map(|value| value as usize),
+            "call_depth" => self.call_depth = value.map(|value| value as usize),
+            "heap_bytes" => self.heap_bytes = value.map(|value| value as usize),
+            "timeout_ms" => self.timeout = value.map(Duration::from_millis),
+            _ => return false
+        }
+        true
+    }
+}
+
+/*
+* Stops a running program from another thread. Vm::interrupt_handle hands
+* out clones that all share one flag; the dispatch loop looks at it every
+* CHECK_INTERVAL instructions and, when it is set, clears it and returns
+* ErrorCode::Cancelled. Vm::run clears it before it starts, so an interrupt
+* sent between runs does not stop the next one. A native function that never returns cannot be
+* stopped this way.
+*/
+#[derive(Clone, Debug, Default)]
+pub struct InterruptHandle {
+    flag: Arc<AtomicBool>
+}
+
+impl InterruptHandle {
+    pub fn interrupt(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    /*
+    * Whether an interrupt was asked for since the last call, clearing it so
+    * that it stops only one run.
+    */
+    pub(crate) fn take(&self) -> bool {
+        self.flag.swap(false, Ordering::Relaxed)
+    }
+}
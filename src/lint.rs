@@ -0,0 +1,265 @@
+// MIT License
+//
+// Copyright (c) 2023 Ramesh Poudel
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+#![allow(dead_code)]
+
+use std::collections::HashSet;
+
+use crate::ast::{Expr, Span, Stmt};
+use crate::diagnostics::{self, Lint, LintConfig, LintLevel};
+use crate::optimizer;
+
+/*
+* Walks a parsed program and reports the warnings from diagnostics::Lint.
+* Warnings come out in source order, except unused variables, which can only
+* be known once the whole program has been seen and so are reported last.
+*/
+pub struct Linter<'linting> {
+    source_file_path: &'linting str,
+    lints: LintConfig,
+    // Names defined before the program runs, such as the Vm's globals and
+    // natives.
+    known: Vec<&'linting str>,
+    // Every name the program creates with 'rakha', wherever it does so.
+    defined: Vec<&'linting str>,
+    // Whether -O1 will leave branches that never run out of the bytecode.
+    optimizing: bool,
+    declared: Vec<(&'linting str, Span)>,
+    read: HashSet<&'linting str>,
+    had_error: bool
+}
+
+impl<'linting> Linter<'linting> {
+    pub fn new(source_file_path: &'linting str, lints: LintConfig) -> Linter<'linting> {
+        Linter {
+            source_file_path,
+            lints,
+            known: Vec::new(),
+            defined: Vec::new(),
+            optimizing: false,
+            declared: Vec::new(),
+            read: HashSet::new(),
+            had_error: false
+        }
+    }
+
+    /*
+    * Names that exist before the program runs and so are not undefined.
+    */
+    pub fn knowing(mut self, names: impl IntoIterator<Item = &'linting str>) -> Linter<'linting> {
+        self.known.extend(names);
+        self
+    }
+
+    pub fn optimizing(mut self, optimizing: bool) -> Linter<'linting> {
+        self.optimizing = optimizing;
+        self
+    }
+
+    /*
+    * Returns false if a lint set to `--deny` fired.
+    */
+    pub fn check(&mut self, program: &'linting [Stmt]) -> bool {
+        Linter::_collect_defined(program, &mut self.defined);
+        self._check_block(program);
+        self._check_unused_vars();
+        !self.had_error
+    }
+
+    /*
+    * Names starting with '_' are exempt, so a variable can be kept around
+    * on purpose without tripping the lint.
+    */
+    fn _check_unused_vars(&mut self) {
+        let mut reported: HashSet<&str> = HashSet::new();
+        for idx in 0..self.declared.len() {
+            let (name, span): (&str, Span) = self.declared[idx];
+            if name.starts_with('_') || self.read.contains(name) || !reported.insert(name) {
+                continue;
+            }
+            self.warn_at(span, Lint::UnusedVariable, &format!("'{}' bhanne variable banaiyo tara kahile pani padhiyena.", name));
+        }
+    }
+
+    fn _collect_defined(body: &'linting [Stmt], defined: &mut Vec<&'linting str>) {
+        for stmt in body {
+            match stmt {
+                Stmt::VarDecl { name, .. } => defined.push(name),
+                Stmt::If { then_branch, else_branch, .. } => {
+                    Linter::_collect_defined(then_branch, defined);
+                    if let Some(else_branch) = else_branch {
+                        Linter::_collect_defined(std::slice::from_ref(else_branch.as_ref()), defined);
+                    }
+                },
+                Stmt::While { body, .. } | Stmt::Block { body, .. } => Linter::_collect_defined(body, defined),
+                _ => ()
+            }
+        }
+    }
+
+    /*
+    * A name neither the program nor the Vm defines fails when it runs. A
+    * host can still define globals after compiling, so this only warns when
+    * a close name to suggest instead exists.
+    */
+    fn _check_defined(&mut self, name: &str, span: Span) {
+        if self.defined.contains(&name) || self.known.contains(&name) {
+            return;
+        }
+        let candidates = self.defined.iter().chain(self.known.iter()).copied();
+        if let Some(close) = diagnostics::suggest(name, candidates) {
+            let message: String = format!("'{}' bhanne variable kahi pani banaiyeko chhaina. {}", name, diagnostics::did_you_mean(close));
+            self.warn_at(span, Lint::UndefinedVariable, &message);
+        }
+    }
+
+    fn _check_block(&mut self, body: &'linting [Stmt]) {
+        let mut diverged: bool = false;
+        let mut reported: bool = false;
+        for stmt in body {
+            if diverged && !reported {
+                self.warn_at(stmt.span(), Lint::UnreachableCode, "'patak' pachhi lekhiyeko yo code kahile pani chaldaina.");
+                reported = true;
+            }
+            diverged |= matches!(stmt, Stmt::Break { .. });
+            self._check_stmt(stmt);
+        }
+    }
+
+    fn _check_stmt(&mut self, stmt: &'linting Stmt) {
+        match stmt {
+            Stmt::Print { value, .. } => self._check_expr(value),
+            Stmt::Expression { expr, .. } => self._check_expr(expr),
+            Stmt::VarDecl { name, initializer, span } => {
+                if let Some((_, first)) = self.declared.iter().find(|(declared, _)| *declared == name) {
+                    let line: usize = first.line;
+                    self.warn_at(*span, Lint::ShadowedVariable, &format!("'{}' line {} ma pahile nai banaiyeko chha; feri 'rakha' garda aghillo variable dhakinchha. Value badalna '{} ma ...;' lekhnus.", name, line, name));
+                }
+                self.declared.push((name, *span));
+                if let Some(initializer) = initializer {
+                    self._check_expr(initializer);
+                }
+            },
+            Stmt::Assign { name, value, span } => {
+                self._check_defined(name, *span);
+                self._check_expr(value);
+            },
+            Stmt::If { condition, then_branch, else_branch, .. } => {
+                self._check_condition(condition, then_branch, else_branch.as_deref());
+                self._check_expr(condition);
+                self._check_block(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self._check_stmt(else_branch);
+                }
+            },
+            Stmt::While { condition, body, .. } => {
+                self._check_expr(condition);
+                self._check_block(body);
+            },
+            Stmt::Break { .. } => (),
+            Stmt::Block { body, .. } => self._check_block(body)
+        }
+    }
+
+    /*
+    * A condition with no variables in it always evaluates the same way,
+    * which is almost always a mistake. When it folds to sahi or galat the
+    * one warning also names the branch that never runs, and says whether
+    * -O1 left it out of the bytecode.
+    */
+    fn _check_condition(&mut self, condition: &Expr, then_branch: &[Stmt], else_branch: Option<&Stmt>) {
+        if Linter::_reads_variable(condition) {
+            return;
+        }
+        let (mut message, removed): (String, bool) = match optimizer::constant_condition(condition) {
+            Some(true) if else_branch.is_some() => (String::from("'yadi' ko condition sadhai sahi hunchha, tesaile yesko 'natra' kahile pani chaldaina."), true),
+            Some(true) => (String::from("'yadi' ko condition sadhai sahi hunchha."), false),
+            Some(false) => (String::from("'yadi' ko condition sadhai galat hunchha, tesaile yesko block kahile pani chaldaina."), !then_branch.is_empty()),
+            None => (String::from("'yadi' ko condition ma kunai variable chhaina, tesaile yo sadhai ustai hunchha."), false)
+        };
+        if removed && self.optimizing {
+            message.push_str(" -O1 le tyo code bytecode ma rakhena.");
+        }
+        self.warn_at(Linter::_first_span(condition), Lint::ConstantCondition, &message);
+    }
+
+    fn _check_expr(&mut self, expr: &'linting Expr) {
+        match expr {
+            Expr::Literal { .. } => (),
+            Expr::Variable { name, span } => {
+                self.read.insert(name);
+                self._check_defined(name, *span);
+            },
+            Expr::Grouping { inner, .. } => self._check_expr(inner),
+            Expr::Unary { operand, .. } => self._check_expr(operand),
+            Expr::Binary { left, right, .. } => {
+                self._check_expr(left);
+                self._check_expr(right);
+            },
+            Expr::Call { callee, args, .. } => {
+                // A call reads the global of that name if there is one.
+                self.read.insert(callee);
+                for arg in args {
+                    self._check_expr(arg);
+                }
+            }
+        }
+    }
+
+    fn _reads_variable(expr: &Expr) -> bool {
+        match expr {
+            Expr::Literal { .. } => false,
+            Expr::Variable { .. } => true,
+            Expr::Grouping { inner, .. } => Linter::_reads_variable(inner),
+            Expr::Unary { operand, .. } => Linter::_reads_variable(operand),
+            Expr::Binary { left, right, .. } => Linter::_reads_variable(left) || Linter::_reads_variable(right),
+            // A native function can return something different every time.
+            Expr::Call { .. } => true
+        }
+    }
+
+    /*
+    * Binary nodes carry the operator's span; warnings about a whole
+    * expression point at where it starts instead.
+    */
+    fn _first_span(expr: &Expr) -> Span {
+        match expr {
+            Expr::Binary { left, .. } => Linter::_first_span(left),
+            _ => expr.span()
+        }
+    }
+
+    fn warn_at(&mut self, span: Span, lint: Lint, message: &str) {
+        let location: String = format!("{}:{}:{}", self.source_file_path, span.line, span.column);
+        match self.lints.level(lint) {
+            LintLevel::Allow => (),
+            LintLevel::Warn => {
+                diagnostics::print_located(diagnostics::Severity::Warning, &format!("Warning[{}]", lint), message, &location, &format!("Yo warning lukauna `--allow {}` dinus.", lint));
+            },
+            LintLevel::Deny => {
+                diagnostics::print_located(diagnostics::Severity::Error, &format!("Compilation error[{}]", lint), message, &location, "`--deny` le yo warning lai error banayo.");
+                self.had_error = true;
+            }
+        }
+    }
+}
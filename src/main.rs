@@ -20,433 +20,535 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
 // THE SOFTWARE.
 
-#![allow(dead_code)]
-#![allow(unused)]
-#![allow(non_camel_case_types)]
+/*
+* The bish command line. Everything it does goes through the bish library;
+* this file only reads the arguments and maps results to exit codes.
+*/
 
-pub mod scanner;
-pub mod compiler;
-pub mod chunk;
-use chunk::{Chunk, PrimType, OpCode};
+use bish::diagnostics::{self, ErrorCode, LintConfig, LintLevel};
+use bish::optimizer::OptLevel;
+use bish::stdlib::ALL_CAPABILITIES;
+use bish::{bytecode, compiler, formatter, repl, Capabilities, Error, Limits, Value, Vm};
 
-use std::collections::HashMap;
+use std::{env, fs};
+use std::ops::RangeInclusive;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn main() {
+    let _args: Vec<String> = env::args().collect();
+    if _args.len() < 2 {
+        let mut vm: Vm = Vm::new();
+        repl::run(&mut vm);
+        return;
+    }
+
+    if _args[1] == "--help" || _args[1] == "-h" {
+        print_usage();
+        return;
+    }
 
-const STACK_MAX: u32 = 256;
+    if _args[1] == "--explain" {
+        explain(_args.get(2));
+        return;
+    }
 
-#[derive(PartialEq, Eq)]
-enum InterpResult {
-    COMPILE_ERROR = 0,
-    RUNTIME_ERROR = 1,
-    INTERPRET_COMPILE_ERROR = 2,
-    OK = 3
+    // `bish <file>` is the same as `bish run <file>`.
+    let command: &str = match _args[1].as_str() {
+        "run" | "check" | "build" | "disasm" | "fmt" | "test" => _args[1].as_str(),
+        _ => "run"
+    };
+    let start: usize = if command == _args[1] { 2 } else { 1 };
+    let options: Options = Options::parse(command, &_args[start..]);
+    match command {
+        "check" => check(&options),
+        "build" => build(&options),
+        "disasm" => disasm(&options),
+        "fmt" => fmt(&options),
+        "test" => test(&options),
+        _ => run(&options)
+    }
 }
 
-struct VirtMac {
-    chunk: chunk::Chunk,
-    ip: usize,
-    stack: Vec<PrimType>,
-    globals: HashMap<String, PrimType>
+/*
+* Where the program comes from: a file, the code given to `-e`, or stdin
+* when the file is `-`.
+*/
+enum Source {
+    File(String),
+    Inline(String),
+    Stdin
 }
 
-impl VirtMac {
-    fn new(chunk: Chunk) -> VirtMac {
-        VirtMac {
-            chunk,
-            ip: 0,
-            stack: Vec::new(),
-            globals: HashMap::<String, PrimType>::new()
+impl Source {
+    fn name(&self) -> &str {
+        match self {
+            Source::File(path) => path,
+            Source::Inline(_) => "<-e>",
+            Source::Stdin => "<stdin>"
         }
     }
 
-    fn stack_push(&mut self, val: PrimType) {
-        // println!("DEBUG[stack_push]: PrimType = {:?}", &val);
-        self.stack.push(val);
-    }
-
-    fn stack_pop(&mut self) -> PrimType {
-        if let Some(value) = &self.stack.pop() {
-            value.clone()
-        }
-        else {
-            PrimType::Unknown
+    fn is_bytecode(&self) -> bool {
+        match self {
+            Source::File(path) => Path::new(path).extension().is_some_and(|ext| ext == bytecode::EXTENSION),
+            _ => false
         }
     }
 
-    fn _dump_stack(&self) {
-        let mut idx: usize = self.stack.len();
-        for i in (0..idx).rev() {
-            match self.stack[i].clone() {
-                PrimType::Integer(value) => println!("[{value}]"),
-                PrimType::Double(value) => println!("[{value}]"),
-                PrimType::Boolean(value) => println!("[{}]", if value { "sahi(true)" } else { "galat(false)" } ),
-                PrimType::CString(len, data) => println!("[{data}({len})]"),
-                PrimType::Nil => println!("[nil]"),
-                PrimType::Unknown => println!("[UNKNOWN]")
+    fn read(&self) -> String {
+        match self {
+            Source::File(path) => match fs::read_to_string(path) {
+                Ok(content) => content,
+                Err(error) => {
+                    diagnostics::fatal(ErrorCode::UnreadableFile, "Error", &format!("Tapaile diyeko file '{}' lai padhna sakiyena: {}", path, error));
+                }
+            },
+            Source::Inline(code) => code.clone(),
+            Source::Stdin => {
+                let mut code: String = String::new();
+                if let Err(error) = io::stdin().read_to_string(&mut code) {
+                    diagnostics::fatal(ErrorCode::UnreadableFile, "Error", &format!("stdin padhna sakiyena: {}", error));
+                }
+                code
             }
         }
     }
+}
 
-    fn compile(&mut self, source_file_path: &str) -> InterpResult {
-        let source_code: String = match fs::read_to_string(source_file_path) {
-            Ok(content) => content,
-            Err(error) => {
-                println!("Tapaile diyeko file lai padhna sakiyena.");
-                std::process::exit(15);
-            }
+/*
+* Everything given on the command line after the subcommand. Every
+* subcommand reads the same flags the same way; one that does not apply to
+* the subcommand is rejected instead of silently ignored.
+*/
+struct Options {
+    sources: Vec<Source>,
+    lints: LintConfig,
+    limits: Limits,
+    capabilities: Capabilities,
+    opt_level: OptLevel,
+    // -O, -A, -W, -D, --limit, --grant and --gc-stress as given, for `test` to pass on to each program.
+    run_flags: Vec<String>,
+    trace: Option<RangeInclusive<usize>>,
+    dump_stack: bool,
+    gc_stress: bool,
+    output_path: Option<String>,
+    fmt_check: bool,
+    // Everything after the program given to `run`, for the program to read.
+    script_args: Vec<String>
+}
+
+impl Options {
+    fn parse(command: &str, args: &[String]) -> Options {
+        let mut options: Options = Options {
+            sources: Vec::new(),
+            lints: LintConfig::new(),
+            limits: Limits::new(),
+            capabilities: Capabilities::new(),
+            opt_level: OptLevel::O1,
+            run_flags: Vec::new(),
+            trace: None,
+            dump_stack: false,
+            gc_stress: false,
+            output_path: None,
+            fmt_check: false,
+            script_args: Vec::new()
         };
-        let mut s: scanner::Scanner = scanner::Scanner::new(source_code);
-        let tokens: Vec<scanner::Token> = s.start_scan();
-        let mut parser: compiler::Parser = compiler::Parser::new(source_file_path.to_owned(), &tokens, &mut self.chunk);
-        if parser.compile() == compiler::CompilationResult::Ok { InterpResult::OK }
-        else { InterpResult::COMPILE_ERROR }
+        let mut idx: usize = 0;
+        while idx < args.len() {
+            // Whatever follows the program being run belongs to it, not to bish.
+            if command == "run" && !options.sources.is_empty() {
+                options.script_args.extend_from_slice(&args[idx..]);
+                break;
+            }
+            let arg: &str = args[idx].as_str();
+            let option: &str = match arg {
+                "-A" | "--allow" | "-W" | "--warn" | "-D" | "--deny" => "lint",
+                _ if arg.starts_with("--trace") => "--trace",
+                _ if OptLevel::parse(arg).is_some() => "-O",
+                _ => arg
+            };
+            if option.starts_with('-') && option != "-" && !takes_option(command, option) {
+                if takes_option("run", option) || takes_option("build", option) || takes_option("fmt", option) {
+                    diagnostics::fatal(ErrorCode::UnknownOption, "Error", &format!("'{}' option '{}' sanga chaldaina.", arg, command));
+                }
+                diagnostics::fatal(ErrorCode::UnknownOption, "Error", &format!("'{}' bhanne option chhaina. Sabai option herna 'bish --help' chalaunus.", arg));
+            }
+            match option {
+                "-o" => options.output_path = Some(Options::_value(args, &mut idx)),
+                "-e" => {
+                    let code: String = Options::_value(args, &mut idx);
+                    options.add_source(command, Source::Inline(code));
+                },
+                "--dump-stack" => options.dump_stack = true,
+                "--gc-stress" => {
+                    options.gc_stress = true;
+                    options.run_flags.push(arg.to_owned());
+                },
+                "--check" => options.fmt_check = true,
+                "--trace" => options.trace = match arg["--trace".len()..].strip_prefix('=') {
+                    None if arg == "--trace" => Some(1..=usize::MAX),
+                    Some(lines) => match parse_line_range(lines) {
+                        Some(lines) => Some(lines),
+                        None => diagnostics::fatal(ErrorCode::UnknownOption, "Error", &format!("'{}' line ko range hoina. '--trace=12' wa '--trace=10-20' jasto lekhnus.", lines))
+                    },
+                    None => diagnostics::fatal(ErrorCode::UnknownOption, "Error", &format!("'{}' bhanne option chhaina.", arg))
+                },
+                "-O" => {
+                    options.opt_level = OptLevel::parse(arg).unwrap();
+                    options.run_flags.push(arg.to_owned());
+                },
+                "lint" => {
+                    let level: LintLevel = match arg {
+                        "-A" | "--allow" => LintLevel::Allow,
+                        "-W" | "--warn" => LintLevel::Warn,
+                        _ => LintLevel::Deny
+                    };
+                    let name: String = Options::_value(args, &mut idx);
+                    if !options.lints.apply(&name, level) {
+                        diagnostics::fatal(ErrorCode::UnknownOption, "Error", &format!("'{}' bhanne lint chhaina. 'warnings' wa yi madhye kunai ek dinus: {}", name,
+                            diagnostics::ALL_LINTS.iter().map(|lint| lint.name()).collect::<Vec<&str>>().join(", ")));
+                    }
+                    options.run_flags.extend([arg.to_owned(), name]);
+                },
+                "--limit" => {
+                    let setting: String = Options::_value(args, &mut idx);
+                    if !options.limits.apply(&setting) {
+                        diagnostics::fatal(ErrorCode::UnknownOption, "Error", &format!("'{}' milena. 'instructions', 'stack', 'call_depth', 'heap_bytes' wa 'timeout_ms' madhye ek lai '<naam>=<sankhya>' wa '<naam>=none' jasto dinus.", setting));
+                    }
+                    options.run_flags.extend([arg.to_owned(), setting]);
+                },
+                "--grant" => {
+                    let name: String = Options::_value(args, &mut idx);
+                    if !options.capabilities.apply(&name) {
+                        diagnostics::fatal(ErrorCode::UnknownOption, "Error", &format!("'{}' bhanne anumati chhaina. 'all' wa yi madhye kunai ek dinus: {}", name,
+                            ALL_CAPABILITIES.iter().map(|capability| capability.name()).collect::<Vec<&str>>().join(", ")));
+                    }
+                    options.run_flags.extend([arg.to_owned(), name]);
+                },
+                "-" => options.add_source(command, Source::Stdin),
+                _ => options.add_source(command, Source::File(arg.to_owned()))
+            }
+            idx += 1;
+        }
+        options
     }
 
-    fn interpret(&mut self, source_file_path: &str) -> InterpResult {
-        if InterpResult::COMPILE_ERROR == self.compile(source_file_path) {
-            println!("compile error. terminated.");
-            std::process::exit(1);
+    /*
+    * The word after an option that takes one, such as the path after `-o`.
+    */
+    fn _value(args: &[String], idx: &mut usize) -> String {
+        *idx += 1;
+        match args.get(*idx) {
+            Some(value) => value.clone(),
+            None => diagnostics::fatal(ErrorCode::UnknownOption, "Error", &format!("'{}' pachhi euta value chahinchha.", args[*idx - 1]))
         }
+    }
 
-        if self.chunk.size < 1 {
-            return InterpResult::OK;
+    fn add_source(&mut self, command: &str, source: Source) {
+        let many: bool = matches!(command, "check" | "fmt" | "test");
+        if !self.sources.is_empty() && !many {
+            diagnostics::fatal(ErrorCode::UnknownOption, "Error", &format!("'{}' le euta matra program linchha, tara '{}' pani diyiyo.", command, source.name()));
+        }
+        if matches!(source, Source::Inline(_) | Source::Stdin) && command == "test" {
+            diagnostics::fatal(ErrorCode::UnknownOption, "Error", "'test' le file wa directory matra linchha.");
         }
+        self.sources.push(source);
+    }
 
-        loop {
-            let code: u8 = self.chunk.code[self.ip];
-            self._interpret_instr(code); 
-            self.ip += 1;
-            if self.ip >= self.chunk.code.len()
-            {
-                break;
-            }
-        } 
-        InterpResult::OK
-    }
-
-    fn _interpret_instr(&mut self, i: u8) {
-        let instr: OpCode = OpCode::from_u8(i);
-        match instr {
-            OpCode::OP_RETURN => { },
-            OpCode::OP_NOP => (),
-            OpCode::OP_CONST => {
-                let con = &self.chunk.read_const();
-                match con {
-                    PrimType::Double(value) => self.stack_push(PrimType::Double(*value)),
-                    PrimType::Integer(value) => self.stack_push(PrimType::Integer(*value)),
-                    PrimType::CString(len, data) => self.stack_push(PrimType::CString(*len, data.clone())),
-                    PrimType::Unknown => {
-                        println!("PANIC: Unknown value type in constant pool!");
-                        std::process::exit(1);
-                    },
-                    _ => ()
-                }
-            },
-            OpCode::OP_TRUE => self.stack_push(PrimType::Boolean(true)),
-            OpCode::OP_FALSE => self.stack_push(PrimType::Boolean(false)),
-            OpCode::OP_NIL => self.stack_push(PrimType::Nil),
-            OpCode::OP_AND | 
-            OpCode::OP_OR | 
-            OpCode::OP_ADD | 
-            OpCode::OP_SUBTRACT | 
-            OpCode::OP_MULTIPLY |
-            OpCode::OP_DIVIDE | 
-            OpCode::OP_EQ_EQ |
-            OpCode::OP_GT |
-            OpCode::OP_LT => self._interpret_binary_instr(instr),
-            OpCode::OP_NEGATE => self._perform_negate_op(),
-            OpCode::OP_NOT => self._perform_not_op(),
-            OpCode::OP_PRINT => self._interpret_print_stmt(),
-            OpCode::OP_POP => { self.stack_pop(); },
-            OpCode::OP_DEF_GLOBAL => {
-                let name: PrimType = self.chunk.read_const();
-                let value: PrimType = self.stack_pop();
-                self._define_global_var(name, value);
-                self.stack_pop();
-            },
-            OpCode::OP_LOAD_GLOBAL => {
-                let name: PrimType = self.chunk.read_const();
-                self._load_global_into_stack(name);
-            },
-            OpCode::OP_JMP_IF_FALSE => {
-                let offset: u16 = self._read_short_from_chunk();
-                self.ip += 2; // jumping offset value
-                let condition: PrimType = self.stack_pop();
-                if let PrimType::Boolean(cond) = condition {
-                    if !cond {
-                        /*
-                        * OP_JMP_IF_FALSE
-                        * OFF_1
-                        * OFF_2 <--- IP
-                        */
-                        self.ip += 1;
-
-                        // Reading and discarding every constant value from the constant pool 
-                        // that are defined inside this 'yadi' statment.
-                        let mut temp_ip: usize = self.ip;
-                        while temp_ip < self.ip + (offset as usize) {
-                            let bytecode: u8 = self.chunk.code[temp_ip];
-                            if OpCode::from_u8(bytecode) == OpCode::OP_CONST {
-                                let _ = self.chunk.read_const();
-                            }
-                            temp_ip += 1;
-                        }
-                        self.ip += (offset as usize);
-                    }
-                }
-            },
-            OpCode::OP_ELSE => {
-                self._perform_else_op();
-            },
-            _ => ()
+    /*
+    * The one program that run, build and disasm work on.
+    */
+    fn source(&self) -> &Source {
+        match self.sources.first() {
+            Some(source) => source,
+            None => usage()
         }
     }
 
-    fn _perform_else_op(&mut self) {
-        let offset: u16 = self._read_short_from_chunk();
-        self.ip += (offset + 2) as usize;
+    fn vm(&self) -> Vm {
+        let mut vm: Vm = Vm::new();
+        vm.lints = self.lints.clone();
+        vm.opt_level = self.opt_level;
+        vm.limits = self.limits;
+        vm.capabilities = self.capabilities.clone();
+        vm.gc.stress = self.gc_stress;
+        vm.trace = self.trace.clone();
+        vm
     }
+}
 
-    fn _load_global_into_stack(&mut self, var_name: PrimType) {
-        #[allow(clippy::single_match)]
-        match var_name {
-            PrimType::CString(_, value) => {
-                let _value = self.globals.get(&value);
-                if let Some(_val) = _value { 
-                    self.stack_push(_val.clone()); 
-                }
-                else {
-                    println!("Runtime error: '{}' bhanne variable pahile banaiyeko chhaina. Kripaya variable use garnu bhanda agadi teslai banaunu hola.", value);
-                    std::process::exit(18);
-                }
-            },
-            _ => ()
-        }
+/*
+* Which options each subcommand takes.
+*/
+fn takes_option(command: &str, option: &str) -> bool {
+    match option {
+        "-O" | "lint" => command != "fmt",
+        "-e" => command != "test",
+        "--trace" | "--dump-stack" => command == "run",
+        "-o" => command == "build",
+        "--check" => command == "fmt",
+        "--limit" | "--grant" | "--gc-stress" => matches!(command, "run" | "test"),
+        _ => false
     }
+}
 
-    fn _define_global_var(&mut self, name: PrimType, value: PrimType) {
-        if let PrimType::CString(_, var_name) = name {
-            self.globals.insert(var_name, value);
-        } 
+fn run(options: &Options) {
+    let source: &Source = options.source();
+    let mut vm: Vm = options.vm();
+    // Before compiling, so that the arguments count as defined names.
+    vm.set_script_args(source.name(), &options.script_args);
+    or_exit(load_or_compile(&mut vm, source));
+    or_exit(vm.run());
+    if options.dump_stack {
+        dump_stack(vm.stack());
     }
+}
 
-    fn _interpret_print_stmt(&mut self) {
-        let value: &PrimType = &self.stack_pop();
-        match value {
-            PrimType::CString(len, value) => println!("{}", value),
-            PrimType::Double(value) => println!("{}", value),
-            PrimType::Integer(value) => println!("{}", value),
-            PrimType::Boolean(value) => println!("{}", if *value { "sahi" } else { "galat" }),
-            PrimType::Nil => println!("nil"),
-            _ => {
-                println!("Can't print");
-                std::process::exit(10);
+/*
+* Compiles every program given, or verifies it if it is a .bishc file,
+* reporting all diagnostics without running anything.
+*/
+fn check(options: &Options) {
+    if options.sources.is_empty() {
+        usage();
+    }
+    let mut failed: bool = false;
+    for source in &options.sources {
+        let mut vm: Vm = options.vm();
+        let result: Result<(), Error> = match source.is_bytecode() {
+            true => vm.load_file(source.name()).and_then(|()| vm.verify()),
+            false => vm.compile(source.name(), source.read())
+        };
+        match result {
+            Ok(()) => (),
+            Err(Error::Compile) => failed = true,
+            Err(error) => {
+                error.report();
+                failed = true;
             }
         }
     }
-    
-    fn _interpret_binary_instr(&mut self, instr: OpCode) {
-        let aa: &PrimType = &self.stack_pop();
-        let bb: &PrimType = &self.stack_pop();
-        let mut ok: bool = true;
-
-        match instr {
-            OpCode::OP_AND | OpCode::OP_OR => {
-                let avalue = match aa {
-                    PrimType::Integer(value) => *value,
-                    _ => {
-                        ok = false;
-                        0
-                    }
-                };
-                let bvalue = match bb {
-                    PrimType::Integer(value) => *value,
-                    _ => {
-                        ok = false;
-                        0
-                    }
-                };
-
-                match ok {
-                    true => self._perform_logical_op(instr, avalue, bvalue),
-                    false => { 
-                        println!(
-                            "Unsupported types for '{}' operation for types: '{:?}' and '{:?}'", 
-                            if instr == OpCode::OP_AND { "ra(&&)" } 
-                            else if instr == OpCode::OP_OR { "wa(||)" } 
-                            else { "unknown" }, 
-                            aa, 
-                            bb
-                        );
-                        std::process::exit(3);
-                    }
-                }
-            },
-            OpCode::OP_ADD |
-            OpCode::OP_SUBTRACT |
-            OpCode::OP_MULTIPLY |
-            OpCode::OP_DIVIDE => {
-                let mut avalue_double: bool = false;
-                let mut bvalue_double: bool = false;
-                let mut avalue_f: f64 = 0.0;
-                let mut bvalue_f: f64 = 0.0;
-                let mut avalue_i: i64 = 0;
-                let mut bvalue_i: i64 = 0;
-                match aa {
-                    PrimType::Double(value) => {
-                        avalue_double = true;
-                        avalue_f = *value;
-                    },
-                    PrimType::Integer(value) => avalue_i = *value,
-                    _ => {}
-                };
-
-                match bb {
-                    PrimType::Double(value) => {
-                        bvalue_double = true;
-                        bvalue_f = *value;
-                    },
-                    PrimType::Integer(value) => bvalue_i = *value,
-                    _ => {}
-                };
-
-                match (avalue_double, bvalue_double) {
-                    (true, true) => self._perform_arithmetic_op_double(instr, avalue_f, bvalue_f),
-                    (true, false) => self._perform_arithmetic_op_double(instr, avalue_f, bvalue_i as f64),
-                    (false, true) => self._perform_arithmetic_op_double(instr, avalue_i as f64, bvalue_f),
-                    (false, false) => self._perform_arithmetic_op_int(instr, avalue_i, bvalue_i)
-                }
-            },
-            OpCode::OP_LT |
-            OpCode::OP_GT |
-            OpCode::OP_EQ_EQ => {
-                self._perform_relational_op(aa, bb, instr);
-            },
-            _ => ()
+    if failed {
+        Error::Compile.report();
+        std::process::exit(1);
+    }
+}
+
+fn build(options: &Options) {
+    let source: &Source = options.source();
+    let output_path: String = match (&options.output_path, source) {
+        (Some(path), _) => path.clone(),
+        (None, Source::File(path)) => Path::new(path).with_extension(bytecode::EXTENSION).to_string_lossy().into_owned(),
+        (None, _) => usage()
+    };
+    let mut vm: Vm = options.vm();
+    or_exit(vm.compile(source.name(), source.read()));
+    let bytes: Vec<u8> = match bytecode::save(vm.chunk()) {
+        Ok(bytes) => bytes,
+        Err(bytecode::SaveError::UnsupportedConstant(index, type_name)) => {
+            diagnostics::fatal(ErrorCode::UnwritableFile, "Error", &format!("'{}' ma lekhna sakiyena: constant {} {} ho, jun .bishc file ma rakhna mildaina.", output_path, index, type_name))
         }
+    };
+    if let Err(error) = fs::write(&output_path, bytes) {
+        diagnostics::fatal(ErrorCode::UnwritableFile, "Error", &format!("'{}' ma lekhna sakiyena: {}", output_path, error));
     }
+}
 
-    fn _perform_not_op(&mut self) {
-        let value: &PrimType = &self.stack_pop();
-        match value {
-            PrimType::Integer(value) => self.stack_push(PrimType::Boolean(*value == 0)),
-            PrimType::Boolean(cond) => self.stack_push(PrimType::Boolean(!cond)),
-            _ => {
-                println!("Type error: can't apply 'chhaina' operator on type '{}'", PrimType::name(value));
-                std::process::exit(9);
-            }
+fn disasm(options: &Options) {
+    let source: &Source = options.source();
+    let mut vm: Vm = options.vm();
+    or_exit(load_or_compile(&mut vm, source));
+    print!("{}", vm.chunk().disassemble(source.name()));
+}
+
+fn load_or_compile(vm: &mut Vm, source: &Source) -> Result<(), Error> {
+    match source.is_bytecode() {
+        true => vm.load_file(source.name()),
+        false => vm.compile(source.name(), source.read())
+    }
+}
+
+fn or_exit<T>(result: Result<T, Error>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(error) => {
+            error.report();
+            std::process::exit(error.exit_code());
         }
     }
+}
 
-    fn _perform_negate_op(&mut self) {
-        let value: &PrimType = &self.stack_pop();
+fn dump_stack(stack: &[Value]) {
+    for value in stack.iter().rev() {
         match value {
-            PrimType::Integer(value) => self.stack_push(PrimType::Integer(-*value)),
-            PrimType::Double(value) => self.stack_push(PrimType::Double(-*value)),
-            _ => {
-                println!("Type error: can't apply negate(-) operator on type '{}'", PrimType::name(value));
-                std::process::exit(8);
-            }
+            Value::Integer(value) => println!("[{value}]"),
+            Value::Double(value) => println!("[{value}]"),
+            Value::Boolean(value) => println!("[{}]", if *value { "sahi(true)" } else { "galat(false)" } ),
+            Value::String(data) => println!("[{data}({})]", data.len()),
+            Value::Array(_) | Value::Map(_) | Value::Function(_) => println!("[{}]", Value::literal(value)),
+            Value::Nil => println!("[nil]"),
+            Value::Unknown => println!("[UNKNOWN]")
         }
     }
+}
 
-    fn _perform_relational_op(&mut self, val1: &PrimType, val2: &PrimType, instr: OpCode) {
-        let result: bool = match instr {
-            OpCode::OP_GT => self._relational_op_gt(val1, val2),
-            OpCode::OP_LT => self._relational_op_lt(val1, val2),
-            OpCode::OP_EQ_EQ => self._relational_op_eq_eq(val1, val2),
-            _ => false
+/*
+* Rewrites files in place in the layout of formatter::format. Code from
+* `-e` or stdin is printed instead. With --check nothing is written; the
+* programs that would change are listed and bish exits with 1.
+*/
+fn fmt(options: &Options) {
+    if options.sources.is_empty() {
+        usage();
+    }
+    let mut failed: bool = false;
+    for source in &options.sources {
+        let code: String = source.read();
+        let formatted: String = match compiler::parse(source.name(), code.clone()) {
+            Some(program) => formatter::format(&program),
+            None => {
+                failed = true;
+                continue;
+            }
         };
-        self.stack_push(PrimType::Boolean(result));
-    }
-
-    fn _relational_op_eq_eq(&mut self, val1: &PrimType, val2: &PrimType) -> bool {
-        match (val1, val2) {
-            (PrimType::Integer(a), PrimType::Integer(b)) => (a == b),
-            (PrimType::Double(a), PrimType::Double(b)) => (a == b),
-            (PrimType::Boolean(cond1), PrimType::Boolean(cond2)) => (cond1 == cond2),
-            (PrimType::CString(len1, val1), PrimType::CString(len2, val2)) => (val1 == val2),
-            _ => {
-                self.panic_type_error("barabar", &PrimType::name(val1), &PrimType::name(val2));
-                false 
+        if options.fmt_check {
+            if formatted != code {
+                println!("{}", source.name());
+                failed = true;
             }
+            continue;
+        }
+        match source {
+            Source::File(path) if formatted != code => {
+                if let Err(error) = fs::write(path, formatted) {
+                    diagnostics::fatal(ErrorCode::UnwritableFile, "Error", &format!("'{}' ma lekhna sakiyena: {}", path, error));
+                }
+            },
+            Source::File(_) => (),
+            _ => print!("{}", formatted)
         }
     }
+    if failed {
+        std::process::exit(1);
+    }
+}
 
-    fn _relational_op_gt(&mut self, val1: &PrimType, val2: &PrimType) -> bool {
-        match (val1, val2) {
-            (PrimType::Integer(a), PrimType::Integer(b)) => (b > a),
-            (PrimType::Double(a), PrimType::Double(b)) => (b > a),
-            _ => {
-                self.panic_type_error("thulo", &PrimType::name(val1), &PrimType::name(val2));
-                false
-            }
-        }
+/*
+* Runs every .bish file given, or found under the directories given
+* (`tests` by default), each in its own bish process. A program passes if
+* it exits with 0 and, when a .out file sits next to it, prints exactly
+* what is in that file.
+*/
+fn test(options: &Options) {
+    let mut roots: Vec<PathBuf> = options.sources.iter().map(|source| PathBuf::from(source.name())).collect();
+    if roots.is_empty() {
+        roots.push(PathBuf::from("tests"));
+    }
+    let mut programs: Vec<PathBuf> = Vec::new();
+    for root in &roots {
+        find_programs(root, &mut programs);
+    }
+    if programs.is_empty() {
+        diagnostics::fatal(ErrorCode::MissingSourceFile, "Error", &format!("{} ma kunai .bish file bhetiyena.",
+            roots.iter().map(|root| format!("'{}'", root.display())).collect::<Vec<String>>().join(", ")));
     }
 
-    fn _relational_op_lt(&mut self, val1: &PrimType, val2: &PrimType) -> bool {
-        match (val1, val2) {
-            (PrimType::Integer(a), PrimType::Integer(b)) => (b < a),
-            (PrimType::Double(a), PrimType::Double(b)) => (b < a),
-            _ => {
-                self.panic_type_error("sano", &PrimType::name(val1), &PrimType::name(val2));
-                false
+    let bish: PathBuf = env::current_exe().unwrap_or_else(|_| PathBuf::from("bish"));
+    let mut failures: usize = 0;
+    for program in &programs {
+        let output: Output = match Command::new(&bish).arg("run").args(&options.run_flags).arg(program).output() {
+            Ok(output) => output,
+            Err(error) => diagnostics::fatal(ErrorCode::UnreadableFile, "Error", &format!("'{}' chalauna sakiyena: {}", program.display(), error))
+        };
+        let stdout: String = String::from_utf8_lossy(&output.stdout).into_owned();
+        let expected: Option<String> = fs::read_to_string(program.with_extension("out")).ok();
+        let problem: Option<String> = if !output.status.success() {
+            Some(format!("exit code {}\n{}", output.status.code().map_or(String::from("signal"), |code| code.to_string()), String::from_utf8_lossy(&output.stderr)))
+        }
+        else {
+            match expected {
+                Some(expected) if expected != stdout => Some(format!("--- aasha gariyeko output\n{}--- aayeko output\n{}", expected, stdout)),
+                _ => None
+            }
+        };
+        match problem {
+            None => println!("test {} ... ok", program.display()),
+            Some(problem) => {
+                println!("test {} ... FAILED\n{}", program.display(), problem);
+                failures += 1;
             }
         }
     }
-
-    fn _perform_logical_op(&mut self, instr: OpCode, avalue: i64, bvalue: i64) {
-        match instr {
-            OpCode::OP_AND => self.stack_push(PrimType::Integer(avalue & bvalue)),
-            OpCode::OP_OR => self.stack_push(PrimType::Integer(avalue | bvalue)),
-            _ => ()
-        }
+    println!("\ntest result: {} pass, {} fail", programs.len() - failures, failures);
+    if failures > 0 {
+        std::process::exit(1);
     }
+}
 
-    fn _perform_arithmetic_op_double(&mut self, instr: OpCode, avalue: f64, bvalue: f64) {
-        match instr {
-            OpCode::OP_ADD => self.stack_push(PrimType::Double(avalue + bvalue)),
-            OpCode::OP_SUBTRACT => self.stack_push(PrimType::Double(bvalue - avalue)),
-            OpCode::OP_DIVIDE => self.stack_push(PrimType::Double(bvalue / avalue)),
-            OpCode::OP_MULTIPLY => self.stack_push(PrimType::Double(bvalue * avalue)),
-            _ => ()
-        }
+/*
+* `path` itself if it is a file, or every .bish file under it, in sorted order.
+*/
+fn find_programs(path: &Path, programs: &mut Vec<PathBuf>) {
+    if !path.is_dir() {
+        programs.push(path.to_path_buf());
+        return;
     }
-    
-    fn _perform_arithmetic_op_int(&mut self, instr: OpCode, avalue: i64, bvalue: i64) {
-        match instr {
-            OpCode::OP_ADD => self.stack_push(PrimType::Integer(avalue + bvalue)),
-            OpCode::OP_SUBTRACT => self.stack_push(PrimType::Integer(bvalue - avalue)),
-            OpCode::OP_DIVIDE => self.stack_push(PrimType::Integer((bvalue as f64 / avalue as f64) as i64)),
-            OpCode::OP_MULTIPLY => self.stack_push(PrimType::Integer(bvalue * avalue)),
-            _ => ()
+    let mut entries: Vec<PathBuf> = match fs::read_dir(path) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect(),
+        Err(error) => diagnostics::fatal(ErrorCode::UnreadableFile, "Error", &format!("'{}' padhna sakiyena: {}", path.display(), error))
+    };
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() || entry.extension().is_some_and(|ext| ext == "bish") {
+            find_programs(&entry, programs);
         }
     }
+}
 
-    fn panic_type_error(&self, op: &str, type1: &str, type2: &str) {
-        println!("Type error: '{}' ra '{}' prakar ko value harulai '{}' operator lagauna mildaina.", type1, type2, op);
-        std::process::exit(7);
+/*
+* "12" or "10-20", as given to `--trace=`.
+*/
+fn parse_line_range(text: &str) -> Option<RangeInclusive<usize>> {
+    let (first, last): (&str, &str) = text.split_once('-').unwrap_or((text, text));
+    let first: usize = first.trim().parse().ok()?;
+    let last: usize = last.trim().parse().ok()?;
+    if first > last {
+        return None;
     }
+    Some(first..=last)
+}
 
-    fn _read_short_from_chunk(&self) -> u16 {
-        let first: u16 = *self.chunk.code.get(self.ip + 1).unwrap() as u16;
-        let second: u16 = *self.chunk.code.get(self.ip + 2).unwrap() as u16;
-        (first << 8) | second
-    }
+fn usage() -> ! {
+    print_usage();
+    ErrorCode::MissingSourceFile.exit();
 }
 
-use std::fs::read_to_string;
-use std::{env, fs};
+fn print_usage() {
+    println!("Usage: cargo run --");
+    println!("       cargo run -- [run] [<options>] [--limit <name>=<n>]... [--grant <group>]... [--gc-stress] [--trace[=<line>[-<line>]]] [--dump-stack] <program> [<arg>...]");
+    println!("       cargo run -- check [<options>] <program>...");
+    println!("       cargo run -- build [<options>] <program> [-o <output.bishc>]");
+    println!("       cargo run -- disasm [<options>] <program>");
+    println!("       cargo run -- fmt [--check] <program>...");
+    println!("       cargo run -- test [<options>] [--limit <name>=<n>]... [--grant <group>]... [--gc-stress] [<file_or_directory>...]");
+    println!("       cargo run -- --explain <code>");
+    println!();
+    println!("<program> is a file, '-' for stdin, or -e <code>.");
+    println!("<options> are -O0|-O1 and --allow|--warn|--deny <lint>|warnings.");
+    println!("--limit caps instructions, stack, call_depth, heap_bytes or timeout_ms; <n> may be 'none'.");
+    println!("--gc-stress collects garbage on every allocation, for testing bish itself.");
+    println!("--grant lets the program use fs, env, clock, random, process or all of them.");
+}
 
-fn main() {
-    let _args: Vec<String> = env::args().collect();
-    if _args.len() < 2 {
-        println!("Usage: cargo run <file_path>");
-        std::process::exit(12);
+fn explain(code: Option<&String>) {
+    let code: &String = match code {
+        Some(code) => code,
+        None => diagnostics::fatal(ErrorCode::UnknownOption, "Error", "'--explain' pachhi error code chahinchha, jastai 'cargo run -- --explain B0007'.")
+    };
+    match ErrorCode::parse(code) {
+        Some(code) => print!("{}: {}", code, code.explanation()),
+        None => diagnostics::fatal(ErrorCode::UnknownOption, "Error", &format!("'{}' bhanne error code chhaina. Code haru 'B0007' jasto dekhinchhan.", code))
     }
-
-    let file_path = &_args.get(1usize).clone();
-    let mut c: Chunk = Chunk::new();
-    let mut vm: VirtMac = VirtMac::new(c);
-    vm.interpret(file_path.unwrap());
-    vm._dump_stack();
 }
\ No newline at end of file
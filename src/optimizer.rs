@@ -0,0 +1,284 @@
+// MIT License
+//
+// Copyright (c) 2023 Ramesh Poudel
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+#![allow(dead_code)]
+
+use crate::ast::{BinaryOp, Expr, Literal, Stmt, UnaryOp};
+use crate::chunk::{Chunk, OpCode};
+
+/*
+* -O0 compiles the tree as written; -O1 (the default) folds constant
+* expressions before codegen and tidies the emitted jumps afterwards.
+*/
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OptLevel {
+    O0,
+    O1
+}
+
+impl OptLevel {
+    pub fn parse(flag: &str) -> Option<OptLevel> {
+        match flag {
+            "-O0" => Some(OptLevel::O0),
+            "-O1" => Some(OptLevel::O1),
+            _ => None
+        }
+    }
+}
+
+/*
+* Replaces operators whose operands are all literals with their result, and
+* a 'yadi' whose condition becomes sahi or galat with the branch that runs.
+* Only combinations the VM would accept are folded, computed the same way
+* the VM computes them; anything that would fail or overflow at runtime is
+* left alone so it still fails at runtime.
+*/
+pub fn fold_constants(program: Vec<Stmt>) -> Vec<Stmt> {
+    program.into_iter().map(fold_stmt).collect()
+}
+
+fn fold_stmt(stmt: Stmt) -> Stmt {
+    match stmt {
+        Stmt::Print { value, span } => Stmt::Print { value: fold_expr(value), span },
+        Stmt::Expression { expr, span } => Stmt::Expression { expr: fold_expr(expr), span },
+        Stmt::VarDecl { name, initializer, span } => Stmt::VarDecl {
+            name,
+            initializer: initializer.map(fold_expr),
+            span
+        },
+        Stmt::Assign { name, value, span } => Stmt::Assign { name, value: fold_expr(value), span },
+        Stmt::If { condition, then_branch, else_branch, span } => {
+            let condition: Expr = fold_expr(condition);
+            // Only the branch that can run is kept; its statements keep their own spans.
+            match condition {
+                Expr::Literal { value: Literal::Boolean(true), .. } => Stmt::Block { body: fold_constants(then_branch), span },
+                Expr::Literal { value: Literal::Boolean(false), .. } => match else_branch {
+                    Some(branch) => fold_stmt(*branch),
+                    None => Stmt::Block { body: Vec::new(), span }
+                },
+                condition => Stmt::If {
+                    condition,
+                    then_branch: fold_constants(then_branch),
+                    else_branch: else_branch.map(|branch| Box::new(fold_stmt(*branch))),
+                    span
+                }
+            }
+        },
+        Stmt::While { condition, body, span } => Stmt::While {
+            condition: fold_expr(condition),
+            body: fold_constants(body),
+            span
+        },
+        Stmt::Break { span } => Stmt::Break { span },
+        Stmt::Block { body, span } => Stmt::Block { body: fold_constants(body), span }
+    }
+}
+
+/*
+* The value a 'yadi' condition always has, if it folds to sahi or galat.
+*/
+pub fn constant_condition(condition: &Expr) -> Option<bool> {
+    match fold_expr(condition.clone()) {
+        Expr::Literal { value: Literal::Boolean(value), .. } => Some(value),
+        _ => None
+    }
+}
+
+fn fold_expr(expr: Expr) -> Expr {
+    match expr {
+        Expr::Grouping { inner, span } => match fold_expr(*inner) {
+            literal @ Expr::Literal { .. } => literal,
+            inner => Expr::Grouping { inner: Box::new(inner), span }
+        },
+        Expr::Unary { op, operand, span } => {
+            let operand: Expr = fold_expr(*operand);
+            if let Expr::Literal { value, .. } = &operand {
+                if let Some(value) = fold_unary(op, value) {
+                    return Expr::Literal { value, span };
+                }
+            }
+            Expr::Unary { op, operand: Box::new(operand), span }
+        },
+        Expr::Binary { op, left, right, span } => {
+            let left: Expr = fold_expr(*left);
+            let right: Expr = fold_expr(*right);
+            if let (Expr::Literal { value: a, .. }, Expr::Literal { value: b, .. }) = (&left, &right) {
+                if let Some(value) = fold_binary(op, a, b) {
+                    return Expr::Literal { value, span };
+                }
+            }
+            Expr::Binary { op, left: Box::new(left), right: Box::new(right), span }
+        },
+        Expr::Call { callee, args, span } => Expr::Call { callee, args: args.into_iter().map(fold_expr).collect(), span },
+        literal_or_variable => literal_or_variable
+    }
+}
+
+fn fold_unary(op: UnaryOp, value: &Literal) -> Option<Literal> {
+    match (op, value) {
+        (UnaryOp::Negate, Literal::Integer(value)) => value.checked_neg().map(Literal::Integer),
+        (UnaryOp::Negate, Literal::Double(value)) => Some(Literal::Double(-value)),
+        (UnaryOp::Not, Literal::Integer(value)) => Some(Literal::Boolean(*value == 0)),
+        (UnaryOp::Not, Literal::Boolean(value)) => Some(Literal::Boolean(!value)),
+        _ => None
+    }
+}
+
+fn fold_binary(op: BinaryOp, a: &Literal, b: &Literal) -> Option<Literal> {
+    match op {
+        BinaryOp::Add | BinaryOp::Subtract | BinaryOp::Multiply | BinaryOp::Divide => match (a, b) {
+            (Literal::Integer(a), Literal::Integer(b)) => fold_arithmetic_int(op, *a, *b).map(Literal::Integer),
+            (Literal::Integer(a), Literal::Double(b)) => Some(Literal::Double(fold_arithmetic_double(op, *a as f64, *b))),
+            (Literal::Double(a), Literal::Integer(b)) => Some(Literal::Double(fold_arithmetic_double(op, *a, *b as f64))),
+            (Literal::Double(a), Literal::Double(b)) => Some(Literal::Double(fold_arithmetic_double(op, *a, *b))),
+            _ => None
+        },
+        BinaryOp::Greater | BinaryOp::Less => {
+            let greater: bool = op == BinaryOp::Greater;
+            match (a, b) {
+                (Literal::Integer(a), Literal::Integer(b)) => Some(Literal::Boolean(if greater { a > b } else { a < b })),
+                (Literal::Double(a), Literal::Double(b)) => Some(Literal::Boolean(if greater { a > b } else { a < b })),
+                _ => None
+            }
+        },
+        BinaryOp::Equal => match (a, b) {
+            (Literal::Integer(a), Literal::Integer(b)) => Some(Literal::Boolean(a == b)),
+            (Literal::Double(a), Literal::Double(b)) => Some(Literal::Boolean(a == b)),
+            (Literal::Boolean(a), Literal::Boolean(b)) => Some(Literal::Boolean(a == b)),
+            (Literal::CString(a), Literal::CString(b)) => Some(Literal::Boolean(a == b)),
+            _ => None
+        },
+        BinaryOp::And | BinaryOp::Or => match (a, b) {
+            (Literal::Integer(a), Literal::Integer(b)) => Some(Literal::Integer(if op == BinaryOp::And { a & b } else { a | b })),
+            _ => None
+        }
+    }
+}
+
+fn fold_arithmetic_int(op: BinaryOp, a: i64, b: i64) -> Option<i64> {
+    match op {
+        BinaryOp::Add => a.checked_add(b),
+        BinaryOp::Subtract => a.checked_sub(b),
+        BinaryOp::Multiply => a.checked_mul(b),
+        BinaryOp::Divide if b != 0 => Some((a as f64 / b as f64) as i64),
+        _ => None
+    }
+}
+
+fn fold_arithmetic_double(op: BinaryOp, a: f64, b: f64) -> f64 {
+    match op {
+        BinaryOp::Add => a + b,
+        BinaryOp::Subtract => a - b,
+        BinaryOp::Multiply => a * b,
+        _ => a / b
+    }
+}
+
+/*
+* Jump targets are absolute offsets into `Chunk.code` here; they are turned
+* back into the relative operands the VM reads when the code is rewritten.
+*/
+fn jump_target(chunk: &Chunk, offset: usize) -> usize {
+    let operand: usize = chunk.read_short(offset + 1) as usize;
+    match opcode_at(&chunk.code, offset) {
+        OpCode::OP_LOOP => offset + 3 - operand,
+        _ => offset + 3 + operand
+    }
+}
+
+/*
+* The peephole pass only ever sees code fresh out of codegen::CodeGen.
+*/
+fn opcode_at(code: &[u8], offset: usize) -> OpCode {
+    OpCode::from_u8(code[offset]).expect("codegen emitted an unknown opcode")
+}
+
+fn is_jump(opcode: &OpCode) -> bool {
+    matches!(opcode, OpCode::OP_JMP_IF_FALSE | OpCode::OP_ELSE | OpCode::OP_LOOP)
+}
+
+/*
+* Cleans up the emitted code:
+*   - a forward jump landing on an OP_ELSE goes straight to where that
+*     OP_ELSE leads, which happens at the end of every nested 'natra yadi',
+*   - an OP_ELSE to the very next instruction (an empty 'natra') is removed,
+*   - OP_NOPs are removed.
+* The line table is rewritten along with the code.
+*/
+pub fn peephole(chunk: &mut Chunk) {
+    let mut offset: usize = 0;
+    while offset < chunk.code.len() {
+        let opcode: OpCode = opcode_at(&chunk.code, offset);
+        if opcode == OpCode::OP_JMP_IF_FALSE || opcode == OpCode::OP_ELSE {
+            let mut target: usize = jump_target(chunk, offset);
+            while target < chunk.code.len() && opcode_at(&chunk.code, target) == OpCode::OP_ELSE {
+                target = jump_target(chunk, target);
+            }
+            let operand: usize = target - offset - 3;
+            chunk.code[offset + 1] = ((operand >> 8) & 0xFF) as u8;
+            chunk.code[offset + 2] = (operand & 0xFF) as u8;
+            if opcode == OpCode::OP_ELSE && operand == 0 {
+                chunk.code[offset..offset + 3].fill(OpCode::OP_NOP as u8);
+            }
+        }
+        offset += 1 + opcode.operand_len();
+    }
+    remove_nops(chunk);
+}
+
+fn remove_nops(chunk: &mut Chunk) {
+    // new_offsets[old] is where the instruction at `old` ends up; a removed
+    // OP_NOP maps to the instruction that follows it.
+    let mut new_offsets: Vec<usize> = vec![0; chunk.code.len() + 1];
+    let mut jumps: Vec<(usize, usize)> = Vec::new();
+    let mut code: Vec<u8> = Vec::with_capacity(chunk.code.len());
+    let mut lines: Vec<usize> = Vec::with_capacity(chunk.lines.len());
+    let mut offset: usize = 0;
+    while offset < chunk.code.len() {
+        let opcode: OpCode = opcode_at(&chunk.code, offset);
+        let width: usize = 1 + opcode.operand_len();
+        new_offsets[offset] = code.len();
+        if opcode != OpCode::OP_NOP {
+            if is_jump(&opcode) {
+                jumps.push((code.len(), jump_target(chunk, offset)));
+            }
+            code.extend_from_slice(&chunk.code[offset..offset + width]);
+            lines.extend_from_slice(&chunk.lines[offset..offset + width]);
+        }
+        offset += width;
+    }
+    new_offsets[chunk.code.len()] = code.len();
+
+    for (offset, old_target) in jumps {
+        let target: usize = new_offsets[old_target];
+        let operand: usize = match opcode_at(&code, offset) {
+            OpCode::OP_LOOP => offset + 3 - target,
+            _ => target - offset - 3
+        };
+        code[offset + 1] = ((operand >> 8) & 0xFF) as u8;
+        code[offset + 2] = (operand & 0xFF) as u8;
+    }
+    chunk.size = code.len();
+    chunk.code = code;
+    chunk.lines = lines;
+}
@@ -0,0 +1,255 @@
+// MIT License
+//
+// Copyright (c) 2023 Ramesh Poudel
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+use crate::ast::{BinaryOp, Expr, Literal, Stmt, UnaryOp};
+use crate::chunk::{Chunk, Value};
+use crate::compiler;
+use crate::diagnostics::{Lint, LintLevel};
+use crate::vm::Vm;
+
+const PROMPT: &str = "bish> ";
+const CONTINUATION_PROMPT: &str = "...   ";
+const SOURCE_NAME: &str = "<repl>";
+
+/*
+* Reads bish code from stdin and runs it one entry at a time. Globals live
+* on in the VM between entries, and the value of an expression entered on
+* its own is echoed back. An entry continues over several lines while it
+* has unclosed braces or parentheses. Entries starting with ':' are
+* commands for looking around the session; ':help' lists them.
+*
+* There is no line editing or history, which would need terminal control
+* std does not offer; `rlwrap bish` provides both.
+*/
+pub fn run(vm: &mut Vm) {
+    let interactive: bool = io::stdin().is_terminal();
+    // Variables are defined in one entry and read in the next, and defining
+    // one again is the usual way to start over, so these would only be noise.
+    vm.lints.set(Lint::UnusedVariable, LintLevel::Allow);
+    vm.lints.set(Lint::ShadowedVariable, LintLevel::Allow);
+
+    let mut stdin = io::stdin().lock();
+    let mut entry: String = String::new();
+    loop {
+        if interactive {
+            print!("{}", if entry.is_empty() { PROMPT } else { CONTINUATION_PROMPT });
+            let _ = io::stdout().flush();
+        }
+        let mut line: String = String::new();
+        match stdin.read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => entry.push_str(&line)
+        }
+        if entry.trim().is_empty() {
+            entry.clear();
+            continue;
+        }
+        if unclosed_depth(&entry) > 0 {
+            continue;
+        }
+        eval(vm, &entry);
+        entry.clear();
+    }
+    if interactive {
+        println!();
+    }
+}
+
+/*
+* Compiles and runs one entry. Errors are reported and the session carries
+* on; statements that ran before a runtime error keep their effects.
+*/
+fn eval(vm: &mut Vm, entry: &str) {
+    if let Some(command) = entry.trim_start().strip_prefix(':') {
+        meta_command(vm, command.trim());
+        return;
+    }
+    if let Some(values) = compile_and_run(vm, SOURCE_NAME, with_semicolon(entry)) {
+        for value in &values {
+            println!("{}", Value::literal(value));
+        }
+    }
+}
+
+/*
+* `1 + 2` is as good as `1 + 2;` at the prompt.
+*/
+fn with_semicolon(code: &str) -> String {
+    let mut source: String = code.trim_end().to_owned();
+    if !source.ends_with(';') && !source.ends_with('}') {
+        source.push(';');
+    }
+    source
+}
+
+fn compile(vm: &mut Vm, source_name: &str, source: String) -> bool {
+    vm.compile(source_name, source).is_ok()
+}
+
+/*
+* Returns the values left by top-level expressions, or None if compiling or
+* running failed (the error has been reported).
+*/
+fn compile_and_run(vm: &mut Vm, source_name: &str, source: String) -> Option<Vec<Value>> {
+    if !compile(vm, source_name, source) {
+        return None;
+    }
+    let result = vm.run();
+    let values: Vec<Value> = std::mem::take(&mut vm.stack);
+    match result {
+        Ok(()) => Some(values),
+        Err(error) => {
+            error.report();
+            None
+        }
+    }
+}
+
+const HELP: &str = "\
+:globals          sabai global variable ra tinko prakar dekhauchha
+:dis <code>       <code> ko bytecode dekhauchha, chalaudaina
+:type <expr>      <expr> ko value kun prakar ko hunchha dekhauchha, chalaudaina
+:load <file>      file lai yahi session ma chalauchha
+:reset            sabai global variable hataudichha
+:time <code>      <code> chalauna kati samay lagyo dekhauchha
+:help             yo suchi";
+
+fn meta_command(vm: &mut Vm, command: &str) {
+    let (name, argument): (&str, &str) = match command.split_once(char::is_whitespace) {
+        Some((name, argument)) => (name, argument.trim()),
+        None => (command, "")
+    };
+    if argument.is_empty() && matches!(name, "dis" | "type" | "load" | "time") {
+        eprintln!("':{}' pachhi ke chahinchha bhanne ':help' ma hernus.", name);
+        return;
+    }
+    match name {
+        "globals" => {
+            let mut globals: Vec<(&str, &Value)> = vm.globals().collect();
+            globals.sort_by_key(|(name, _)| *name);
+            for (name, value) in globals {
+                println!("{}: {} = {}", name, Value::name(value), Value::literal(value));
+            }
+        },
+        "dis" => {
+            // The session's last chunk stays what Vm::chunk returns.
+            let previous: Chunk = std::mem::replace(&mut vm.chunk, Chunk::new());
+            if compile(vm, SOURCE_NAME, with_semicolon(argument)) {
+                print!("{}", vm.chunk.disassemble(SOURCE_NAME));
+            }
+            vm.chunk = previous;
+        },
+        "type" => {
+            let expr: Expr = match compiler::parse(SOURCE_NAME, with_semicolon(argument)).and_then(|mut program| program.pop()) {
+                Some(Stmt::Expression { expr, .. }) => expr,
+                Some(_) => return eprintln!("':type' lai euta expression chahinchha."),
+                None => return
+            };
+            match static_type(vm, &expr) {
+                Some(name) => println!("{}", name),
+                None => eprintln!("Yo expression ko prakar nachalai thaha hudaina.")
+            }
+        },
+        "load" => match fs::read_to_string(argument) {
+            Ok(source) => { compile_and_run(vm, argument, source); },
+            Err(error) => eprintln!("'{}' padhna sakiyena: {}", argument, error)
+        },
+        "reset" => {
+            vm.reset();
+        },
+        "time" => {
+            let started: Instant = Instant::now();
+            if let Some(values) = compile_and_run(vm, SOURCE_NAME, with_semicolon(argument)) {
+                let elapsed: Duration = started.elapsed();
+                for value in &values {
+                    println!("{}", Value::literal(value));
+                }
+                println!("samay: {:?}", elapsed);
+            }
+        },
+        "help" => println!("{}", HELP),
+        _ => eprintln!("':{}' bhanne command chhaina. ':help' le sabai command dekhauchha.", name)
+    }
+}
+
+/*
+* The type `expr` would have, worked out from the globals as they are now
+* without running anything. None when only running it would tell, as for
+* what a native function returns, or when the operands do not fit the
+* operator.
+*/
+fn static_type(vm: &Vm, expr: &Expr) -> Option<String> {
+    let typ: &str = match expr {
+        Expr::Literal { value, .. } => match value {
+            Literal::Integer(_) => "int",
+            Literal::Double(_) => "float",
+            Literal::Boolean(_) => "bool",
+            Literal::CString(_) => "string",
+            Literal::Nil => "nil"
+        },
+        Expr::Variable { name, .. } => match vm.get_global(name) {
+            Some(value) => return Some(Value::name(value)),
+            None if vm.has_native(name) => "function",
+            None => return None
+        },
+        Expr::Grouping { inner, .. } => return static_type(vm, inner),
+        Expr::Unary { op, operand, .. } => match (op, static_type(vm, operand)?.as_str()) {
+            (UnaryOp::Negate, "int") => "int",
+            (UnaryOp::Negate, "float") => "float",
+            (UnaryOp::Not, "int" | "bool") => "bool",
+            _ => return None
+        },
+        Expr::Binary { op, left, right, .. } => match (op, static_type(vm, left)?.as_str(), static_type(vm, right)?.as_str()) {
+            (BinaryOp::Greater | BinaryOp::Less, left, right) if left == right && matches!(left, "int" | "float") => "bool",
+            (BinaryOp::Equal, left, right) if left == right && left != "nil" => "bool",
+            (BinaryOp::And | BinaryOp::Or, "int", "int") => "int",
+            (BinaryOp::Add | BinaryOp::Subtract | BinaryOp::Multiply | BinaryOp::Divide, "int", "int") => "int",
+            (BinaryOp::Add | BinaryOp::Subtract | BinaryOp::Multiply | BinaryOp::Divide, "int" | "float", "int" | "float") => "float",
+            _ => return None
+        },
+        Expr::Call { .. } => return None
+    };
+    Some(String::from(typ))
+}
+
+/*
+* How many '{' and '(' are still open at the end of `source`, ignoring any
+* inside string literals.
+*/
+fn unclosed_depth(source: &str) -> i64 {
+    let mut depth: i64 = 0;
+    let mut in_string: bool = false;
+    for chr in source.chars() {
+        match chr {
+            '"' => in_string = !in_string,
+            '{' | '(' if !in_string => depth += 1,
+            '}' | ')' if !in_string => depth -= 1,
+            _ => ()
+        }
+    }
+    depth
+}
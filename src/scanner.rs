@@ -21,9 +21,10 @@
 // THE SOFTWARE.
 
 #[allow(unused)]
-
 use std::collections::HashMap;
 
+use crate::diagnostics::{self, ErrorCode};
+
 #[derive(Copy, Clone, PartialEq, Hash, Eq, Debug)]
 pub enum TokenType {
     TOKEN_FLOAT_NUM,
@@ -93,41 +94,52 @@ impl Token {
     }
 }
 
+pub const KEYWORDS: [(&str, TokenType); 14] = [
+    ("ghumau", TokenType::TOKEN_GHUMAU),
+    ("patak", TokenType::TOKEN_PATAK),
+    ("rakha", TokenType::TOKEN_RAKHA),
+    ("dekhau", TokenType::TOKEN_DEKHAU),
+    ("ma", TokenType::TOKEN_MA),
+    ("sahi", TokenType::TOKEN_SAHI),
+    ("galat", TokenType::TOKEN_GALAT),
+    ("nil", TokenType::TOKEN_NIL),
+    ("thulo", TokenType::TOKEN_THULO),
+    ("sano", TokenType::TOKEN_SANO),
+    ("barabar", TokenType::TOKEN_BARABAR),
+    ("chhaina", TokenType::TOKEN_CHHAINA),
+    ("yadi", TokenType::TOKEN_YADI),
+    ("natra", TokenType::TOKEN_NATRA)
+];
+
 pub struct Scanner {
     current: usize,
     start: usize,
     line: usize,
     column_counter: usize,
+    // The column the token being scanned starts at.
+    start_column: usize,
     source: String,
-    keywords: HashMap<String, TokenType>
+    keywords: HashMap<String, TokenType>,
+    pub had_error: bool
 }
 
 // static-like methods
 impl Scanner {
     pub fn new(source: String) -> Scanner {
         let mut keywords: HashMap<String, TokenType> = HashMap::new();
-        keywords.insert(String::from("ghumau"), TokenType::TOKEN_GHUMAU);
-        keywords.insert(String::from("patak"), TokenType::TOKEN_PATAK);
-        keywords.insert(String::from("rakha"), TokenType::TOKEN_RAKHA);
-        keywords.insert(String::from("dekhau"), TokenType::TOKEN_DEKHAU);
-        keywords.insert(String::from("ma"), TokenType::TOKEN_MA);
-        keywords.insert(String::from("sahi"), TokenType::TOKEN_SAHI);
-        keywords.insert(String::from("galat"), TokenType::TOKEN_GALAT);
-        keywords.insert(String::from("nil"), TokenType::TOKEN_NIL);
-        keywords.insert(String::from("thulo"), TokenType::TOKEN_THULO);
-        keywords.insert(String::from("sano"), TokenType::TOKEN_SANO);
-        keywords.insert(String::from("barabar"), TokenType::TOKEN_BARABAR);
-        keywords.insert(String::from("chhaina"), TokenType::TOKEN_CHHAINA);
-        keywords.insert(String::from("yadi"), TokenType::TOKEN_YADI);
-        keywords.insert(String::from("natra"), TokenType::TOKEN_NATRA);
+        for (keyword, token_type) in KEYWORDS {
+            keywords.insert(String::from(keyword), token_type);
+        }
 
         Scanner {
             current: 0,
             start: 0,
             line: 1,
             column_counter: 1,
+            start_column: 1,
             source,
-            keywords
+            keywords,
+            had_error: false
         }
     }
 }
@@ -137,6 +149,7 @@ impl Scanner {
         let mut result: Vec<Token> = Vec::new();
         while !self.is_at_end() {
             self.start = self.current;
+            self.start_column = self.column_counter;
             let token: Token = self.scan_token();
             match token.token_type {
                 TokenType::TOKEN_NONE => (),
@@ -159,7 +172,7 @@ impl Scanner {
             lexeme,
             literal,
             line: self.line,
-            column: self.column_counter
+            column: self.start_column
         }
     }
 
@@ -174,6 +187,9 @@ impl Scanner {
         else if chr == ';' {
             self._non_literal_token(TokenType::TOKEN_SEMICOLON, String::from(";"))
         }
+        else if chr == ',' {
+            self._non_literal_token(TokenType::TOKEN_COMMA, String::from(","))
+        }
         else if chr == '.' {
             self._non_literal_token(TokenType::TOKEN_DOT, String::from("."))
         }
@@ -181,31 +197,31 @@ impl Scanner {
             self._non_literal_token(TokenType::TOKEN_STAR, String::from("*"))
         }
         else if chr == '/' {
-            Token::new(TokenType::TOKEN_SLASH, String::from("/"), Option::<_>::None, self.line, self.column_counter)
+            Token::new(TokenType::TOKEN_SLASH, String::from("/"), Option::<_>::None, self.line, self.start_column)
         }
         else if chr == '&' {
-            Token::new(TokenType::TOKEN_AND, String::from("&"), Option::<_>::None, self.line, self.column_counter)
+            Token::new(TokenType::TOKEN_AND, String::from("&"), Option::<_>::None, self.line, self.start_column)
         }
         else if chr == '|' {
-            Token::new(TokenType::TOKEN_OR, String::from("|"), Option::<_>::None, self.line, self.column_counter)
+            Token::new(TokenType::TOKEN_OR, String::from("|"), Option::<_>::None, self.line, self.start_column)
         }
         else if chr == '{' {
-            Token::new(TokenType::TOKEN_LEFT_BRACE, String::from("{"), Option::<_>::None, self.line, self.column_counter)
+            Token::new(TokenType::TOKEN_LEFT_BRACE, String::from("{"), Option::<_>::None, self.line, self.start_column)
         }
         else if chr == '}' {
-            Token::new(TokenType::TOKEN_RIGHT_BRACE, String::from("}"), Option::<_>::None, self.line, self.column_counter)
+            Token::new(TokenType::TOKEN_RIGHT_BRACE, String::from("}"), Option::<_>::None, self.line, self.start_column)
         }
         else if chr == '[' {
-            Token::new(TokenType::TOKEN_LEFT_BRACEKT, String::from("["), Option::<_>::None, self.line, self.column_counter)
+            Token::new(TokenType::TOKEN_LEFT_BRACEKT, String::from("["), Option::<_>::None, self.line, self.start_column)
         }
         else if chr == ']' {
-            Token::new(TokenType::TOKEN_RIGHT_BRACKET, String::from("]"), Option::<_>::None, self.line, self.column_counter)
+            Token::new(TokenType::TOKEN_RIGHT_BRACKET, String::from("]"), Option::<_>::None, self.line, self.start_column)
         }
         else if chr == '(' {
-            Token::new(TokenType::TOKEN_LEFT_PAREN, String::from("("), Option::<_>::None, self.line, self.column_counter)
+            Token::new(TokenType::TOKEN_LEFT_PAREN, String::from("("), Option::<_>::None, self.line, self.start_column)
         }
         else if chr == ')' {
-            Token::new(TokenType::TOKEN_RIGHT_PAREN, String::from(")"), Option::<_>::None, self.line, self.column_counter)
+            Token::new(TokenType::TOKEN_RIGHT_PAREN, String::from(")"), Option::<_>::None, self.line, self.start_column)
         }
         else if chr == '_' || chr.is_alphabetic() {
             while (*self.peek() as char).is_alphanumeric() || (*self.peek() as char) == '_' {
@@ -216,24 +232,23 @@ impl Scanner {
             match token_type {
                 Option::Some(value) => self._non_literal_token(*value, String::from(ident)),
                 Option::None => self._non_literal_token(TokenType::TOKEN_IDENTIFIER, String::from(ident))
-            };
-            Token::none()
+            }
         }
         else if chr == '"' {
-            let _ = self.advance();
+            let line: usize = self.line;
             let output: &str = self._parse_string();
-            Token::new(TokenType::TOKEN_STRING, String::from(output), Option::Some(String::from(output)), self.line, self.column_counter)
+            Token::new(TokenType::TOKEN_STRING, String::from(output), Option::Some(String::from(output)), line, self.start_column)
         }
         else if chr.is_ascii_digit() {
             let (number, is_double): (&str, bool) = self._parse_number();
             match is_double {
-                true => Token::new(TokenType::TOKEN_FLOAT_NUM, String::from(number), Option::Some(String::from(number)), self.line, self.column_counter),
-                false => Token::new(TokenType::TOKEN_INT_NUM, String::from(number), Option::Some(String::from(number)), self.line, self.column_counter)
+                true => Token::new(TokenType::TOKEN_FLOAT_NUM, String::from(number), Option::Some(String::from(number)), self.line, self.start_column),
+                false => Token::new(TokenType::TOKEN_INT_NUM, String::from(number), Option::Some(String::from(number)), self.line, self.start_column)
             }
         }
         else if chr == '\n' {
             self.line += 1;
-            self.column_counter = 0;
+            self.column_counter = 1;
             Token::none()
         }
         else {
@@ -246,14 +261,23 @@ impl Scanner {
         &self.source[self.start + 1..self.current]
     }
 
+    /*
+    * A string may span lines; the line count goes on inside it so the tokens
+    * after it are placed right.
+    */
     fn _parse_string(&mut self) -> &str  {
+        let start_line: usize = self.line;
         while !self.is_at_end() && (*self.peek() as char) != '"' {
-            let _ = self.advance();
+            if *self.advance() == b'\n' {
+                self.line += 1;
+                self.column_counter = 1;
+            }
         }
 
         if self.is_at_end() {
-            println!("Unterminated string!");
-            std::process::exit(4);
+            diagnostics::report(ErrorCode::UnterminatedString, "Syntax error", &format!("line {} ma suru bhayeko string kahile pani antya bhayena.", start_line));
+            self.had_error = true;
+            return "";
         }
         let current: usize = self.current;
         let _ = self.advance();
@@ -0,0 +1,216 @@
+// MIT License
+//
+// Copyright (c) 2023 Ramesh Poudel
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+#![allow(clippy::new_without_default)]
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::chunk::Value;
+use crate::diagnostics::RuntimeError;
+use crate::vm::{NativeFn, Vm};
+
+/*
+* A group of natives that reach outside the Vm. A program can only call a
+* native whose group the host has granted; natives without a group, such as
+* the ones a host registers with Vm::register_native, are always callable.
+*/
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Capability {
+    Fs,
+    Env,
+    Clock,
+    Random,
+    Process
+}
+
+pub const ALL_CAPABILITIES: [Capability; 5] = [
+    Capability::Fs,
+    Capability::Env,
+    Capability::Clock,
+    Capability::Random,
+    Capability::Process
+];
+
+impl Capability {
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::Fs => "fs",
+            Capability::Env => "env",
+            Capability::Clock => "clock",
+            Capability::Random => "random",
+            Capability::Process => "process"
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Capability> {
+        ALL_CAPABILITIES.iter().copied().find(|capability| capability.name() == name)
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/*
+* The groups of natives a Vm lets programs call. A new Vm grants none of
+* them, so a program can only compute and print until the host says
+* otherwise.
+*/
+#[derive(Clone, Debug)]
+pub struct Capabilities {
+    granted: HashSet<Capability>
+}
+
+impl Capabilities {
+    pub fn new() -> Capabilities {
+        Capabilities {
+            granted: HashSet::new()
+        }
+    }
+
+    pub fn all() -> Capabilities {
+        Capabilities {
+            granted: HashSet::from(ALL_CAPABILITIES)
+        }
+    }
+
+    #[inline]
+    pub fn allows(&self, capability: Capability) -> bool {
+        self.granted.contains(&capability)
+    }
+
+    #[inline]
+    pub fn grant(&mut self, capability: Capability) {
+        self.granted.insert(capability);
+    }
+
+    #[inline]
+    pub fn revoke(&mut self, capability: Capability) {
+        self.granted.remove(&capability);
+    }
+
+    /*
+    * Applies one `--grant` flag. `name` is either a group name or "all".
+    * Returns false when the name is not recognised.
+    */
+    pub fn apply(&mut self, name: &str) -> bool {
+        if name == "all" {
+            *self = Capabilities::all();
+            return true;
+        }
+        match Capability::parse(name) {
+            Some(capability) => {
+                self.grant(capability);
+                true
+            },
+            None => false
+        }
+    }
+}
+
+/*
+* The natives every Vm starts with, by group.
+*/
+pub const BUILTINS: [(Capability, &str, usize, NativeFn); 6] = [
+    (Capability::Fs, "padh", 1, padh),
+    (Capability::Fs, "lekh", 2, lekh),
+    (Capability::Env, "vatavaran", 1, vatavaran),
+    (Capability::Clock, "samay", 0, samay),
+    (Capability::Random, "jathabhavi", 0, jathabhavi),
+    (Capability::Process, "chalau", 1, chalau)
+];
+
+fn _text(args: &[Value], idx: usize) -> Result<&str, RuntimeError> {
+    match &args[idx] {
+        Value::String(text) => Ok(text),
+        other => Err(RuntimeError::native(format!("{} number ko argument string hunuparchha, {} hoina", idx + 1, Value::name(other))))
+    }
+}
+
+/*
+* padh(path): the contents of a file.
+*/
+fn padh(_vm: &mut Vm, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::from(fs::read_to_string(_text(args, 0)?)?))
+}
+
+/*
+* lekh(path, text): writes `text` to a file, replacing what it held.
+*/
+fn lekh(_vm: &mut Vm, args: &[Value]) -> Result<Value, RuntimeError> {
+    fs::write(_text(args, 0)?, _text(args, 1)?)?;
+    Ok(Value::Nil)
+}
+
+/*
+* vatavaran(name): an environment variable, or nil when it is not set.
+*/
+fn vatavaran(_vm: &mut Vm, args: &[Value]) -> Result<Value, RuntimeError> {
+    match std::env::var(_text(args, 0)?) {
+        Ok(value) => Ok(Value::from(value)),
+        Err(_) => Ok(Value::Nil)
+    }
+}
+
+/*
+* samay(): seconds since the Unix epoch.
+*/
+fn samay(_vm: &mut Vm, _args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Double(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs_f64()))
+}
+
+/*
+* jathabhavi(): a random float from 0 up to but not including 1. Not fit
+* for anything that needs to be secure.
+*/
+fn jathabhavi(_vm: &mut Vm, _args: &[Value]) -> Result<Value, RuntimeError> {
+    // Every RandomState is seeded differently, which is all the randomness
+    // std offers.
+    let bits: u64 = RandomState::new().build_hasher().finish();
+    Ok(Value::Double((bits >> 11) as f64 / (1u64 << 53) as f64))
+}
+
+/*
+* chalau(command): runs a program, with its arguments separated by spaces,
+* and returns what it printed. No shell is involved.
+*/
+fn chalau(_vm: &mut Vm, args: &[Value]) -> Result<Value, RuntimeError> {
+    let mut words = _text(args, 0)?.split_whitespace();
+    let program: &str = match words.next() {
+        Some(program) => program,
+        None => return Err(RuntimeError::native("chalaune program ko naam chahinchha"))
+    };
+    let output = Command::new(program).args(words).output()?;
+    if !output.status.success() {
+        return Err(RuntimeError::native(format!("'{}' asafal bhayo ({})", program, output.status)));
+    }
+    Ok(Value::from(String::from_utf8_lossy(&output.stdout).into_owned()))
+}
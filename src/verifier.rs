@@ -0,0 +1,185 @@
+// MIT License
+//
+// Copyright (c) 2023 Ramesh Poudel
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+#![allow(dead_code)]
+
+use crate::chunk::{Chunk, OpCode, Value};
+
+/*
+* Where in the chunk verification failed, and why. `line` is None for
+* chunks built without a line table.
+*/
+#[derive(Debug)]
+pub struct VerifyError {
+    pub offset: usize,
+    pub line: Option<usize>,
+    pub message: String
+}
+
+/*
+* Checks a chunk before vm::Vm runs it, so that the VM can trust what it
+* reads:
+*   - every byte at an instruction start is a known opcode the VM runs, and
+*     its operands are inside the code,
+*   - constant operands index into the pool, and global operands name a
+*     string constant,
+*   - every jump lands on the start of an instruction or the end of code,
+*   - no instruction pops more values than the stack holds, and every path
+*     reaching an instruction arrives with the same stack depth.
+*/
+pub fn verify(chunk: &Chunk) -> Result<(), VerifyError> {
+    let starts: Vec<bool> = decode(chunk)?;
+
+    // depths[offset] is the stack depth on entry to the instruction at
+    // `offset`, once some path reaching it has been followed.
+    let mut depths: Vec<Option<usize>> = vec![None; chunk.code.len() + 1];
+    let mut pending: Vec<(usize, usize)> = vec![(0, 0)];
+    while let Some((offset, depth)) = pending.pop() {
+        match depths[offset] {
+            Some(known) if known == depth => continue,
+            Some(known) => {
+                return Err(error_at(chunk, offset, format!("yaha aaipugne bato anusar stack ma {} wa {} value hunchhan; sabai bato barabar hunu parchha", known.min(depth), known.max(depth))));
+            },
+            None => depths[offset] = Some(depth)
+        }
+        if offset == chunk.code.len() {
+            continue;
+        }
+        let opcode: OpCode = OpCode::from_u8(chunk.code[offset]).unwrap();
+        let (pops, pushes): (usize, usize) = stack_effect(chunk, offset);
+        if depth < pops {
+            return Err(error_at(chunk, offset, format!("{:?} lai {} value chahinchha tara stack ma {} matra chha", opcode, pops, depth)));
+        }
+        let depth: usize = depth - pops + pushes;
+        let next: usize = offset + 1 + opcode.operand_len();
+        match opcode {
+            OpCode::OP_JMP_IF_FALSE => {
+                pending.push((jump_target(chunk, offset, &starts)?, depth));
+                pending.push((next, depth));
+            },
+            OpCode::OP_ELSE | OpCode::OP_LOOP => pending.push((jump_target(chunk, offset, &starts)?, depth)),
+            _ => pending.push((next, depth))
+        }
+    }
+    Ok(())
+}
+
+/*
+* Walks the code once from the start, checking each instruction on its own.
+* Returns which offsets begin an instruction.
+*/
+fn decode(chunk: &Chunk) -> Result<Vec<bool>, VerifyError> {
+    let mut starts: Vec<bool> = vec![false; chunk.code.len() + 1];
+    let mut offset: usize = 0;
+    while offset < chunk.code.len() {
+        starts[offset] = true;
+        let byte: u8 = chunk.code[offset];
+        let opcode: OpCode = match OpCode::from_u8(byte) {
+            Some(OpCode::OP_LTE | OpCode::OP_GTE | OpCode::OP_EQ) => {
+                return Err(error_at(chunk, offset, format!("{:?} opcode VM le chalaudaina", OpCode::from_u8(byte).unwrap())));
+            },
+            Some(opcode) => opcode,
+            None => return Err(error_at(chunk, offset, format!("{} (0x{:02x}) kunai opcode hoina", byte, byte)))
+        };
+        let next: usize = offset + 1 + opcode.operand_len();
+        if next > chunk.code.len() {
+            return Err(error_at(chunk, offset, format!("{:?} ko operand code sakiye pachhi samma pugchha", opcode)));
+        }
+        match opcode {
+            OpCode::OP_CONST => {
+                let index: u16 = chunk.read_short(offset + 1);
+                if index as usize >= chunk.const_pool.size {
+                    return Err(error_at(chunk, offset, format!("constant {} chhaina; pool ma {} wata constant matra chhan", index, chunk.const_pool.size)));
+                }
+            },
+            OpCode::OP_DEF_GLOBAL | OpCode::OP_LOAD_GLOBAL | OpCode::OP_SET_GLOBAL | OpCode::OP_CALL => {
+                let index: u16 = chunk.read_short(offset + 1);
+                if index as usize >= chunk.const_pool.size {
+                    return Err(error_at(chunk, offset, format!("constant {} chhaina; pool ma {} wata constant matra chhan", index, chunk.const_pool.size)));
+                }
+                if !matches!(chunk.read_const(index), Value::String(_)) {
+                    return Err(error_at(chunk, offset, format!("{:?} ko constant {} naam (string) hoina", opcode, index)));
+                }
+            },
+            _ => ()
+        }
+        offset = next;
+    }
+    starts[chunk.code.len()] = true;
+    Ok(starts)
+}
+
+fn jump_target(chunk: &Chunk, offset: usize, starts: &[bool]) -> Result<usize, VerifyError> {
+    let opcode: OpCode = OpCode::from_u8(chunk.code[offset]).unwrap();
+    let operand: usize = chunk.read_short(offset + 1) as usize;
+    let next: usize = offset + 3;
+    let target: Option<usize> = match opcode {
+        OpCode::OP_LOOP => next.checked_sub(operand),
+        _ => Some(next + operand)
+    };
+    match target {
+        Some(target) if target < starts.len() && starts[target] => Ok(target),
+        Some(target) if target < starts.len() => Err(error_at(chunk, offset, format!("{:?} 0x{:04x} ma jump garchha, jun kunai instruction ko suru hoina", opcode, target))),
+        _ => Err(error_at(chunk, offset, format!("{:?} ko jump code ko simana ({} bytes) bahira jaanchha", opcode, chunk.code.len())))
+    }
+}
+
+/*
+* How many values the instruction at `offset` pops, then pushes.
+*/
+fn stack_effect(chunk: &Chunk, offset: usize) -> (usize, usize) {
+    let opcode: OpCode = OpCode::from_u8(chunk.code[offset]).unwrap();
+    match opcode {
+        OpCode::OP_CONST |
+        OpCode::OP_TRUE |
+        OpCode::OP_FALSE |
+        OpCode::OP_NIL |
+        OpCode::OP_LOAD_GLOBAL => (0, 1),
+        OpCode::OP_AND |
+        OpCode::OP_OR |
+        OpCode::OP_ADD |
+        OpCode::OP_SUBTRACT |
+        OpCode::OP_MULTIPLY |
+        OpCode::OP_DIVIDE |
+        OpCode::OP_EQ_EQ |
+        OpCode::OP_LT |
+        OpCode::OP_GT => (2, 1),
+        OpCode::OP_NEGATE |
+        OpCode::OP_NOT => (1, 1),
+        OpCode::OP_PRINT |
+        OpCode::OP_POP |
+        OpCode::OP_DEF_GLOBAL |
+        OpCode::OP_SET_GLOBAL |
+        OpCode::OP_JMP_IF_FALSE => (1, 0),
+        OpCode::OP_CALL => (chunk.code[offset + 3] as usize, 1),
+        _ => (0, 0)
+    }
+}
+
+fn error_at(chunk: &Chunk, offset: usize, message: String) -> VerifyError {
+    VerifyError {
+        offset,
+        line: chunk.lines.get(offset).copied(),
+        message
+    }
+}
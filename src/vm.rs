@@ -0,0 +1,1079 @@
+// MIT License
+//
+// Copyright (c) 2023 Ramesh Poudel
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+#![allow(unused)]
+#![allow(clippy::new_without_default)]
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::mem;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Instant;
+
+use crate::chunk::{Chunk, OpCode, Value};
+use crate::diagnostics::{self, ErrorCode, LintConfig, RuntimeError};
+use crate::gc::Heap;
+use crate::intern::{Interner, Symbol};
+use crate::limits::{self, InterruptHandle, Limits};
+use crate::optimizer::{self, OptLevel};
+use crate::stdlib::{self, Capabilities, Capability};
+use crate::{ast, bytecode, codegen, compiler, lint, scanner, verifier};
+
+/*
+* A bish virtual machine. Globals outlive each program, so a Vm can compile
+* and run one piece of code after another in the same session:
+*
+*   let mut vm = Vm::new();
+*   vm.set_global("naam", Value::from("bish"));
+*   vm.eval("rakha x ma 2 * 21;")?;
+*   assert_eq!(vm.eval("x + 1;")?, Some(Value::Integer(43)));
+*
+* Compile errors are printed to stderr as they are found, like on the command
+* line, and come back as Error::Compile.
+*/
+pub struct Vm {
+    pub(crate) chunk: Chunk,
+    pub(crate) ip: usize,
+    pub(crate) stack: Vec<Value>,
+    // Globals by the Symbol of their name; None is not defined.
+    pub(crate) globals: Vec<Option<Value>>,
+    symbols: Interner,
+    // The Symbol of every string constant in `chunk`, by constant index,
+    // so instructions naming a global go straight to its slot.
+    const_symbols: Vec<Option<Symbol>>,
+    natives: HashMap<Symbol, Rc<Native>>,
+    // The arrays and maps allocated through new_array and new_map.
+    pub gc: Heap,
+    // Where 'dekhau' writes; stdout unless set_output says otherwise.
+    output: Box<dyn Write>,
+    pub lints: LintConfig,
+    pub opt_level: OptLevel,
+    pub limits: Limits,
+    // Which groups of natives programs may call.
+    pub capabilities: Capabilities,
+    // Instructions executed and native calls in progress, for `limits`.
+    // Programs can only call natives, so call_depth only grows past one
+    // when a native runs bish code that calls another.
+    executed: u64,
+    call_depth: usize,
+    // The programs waiting on a native that is itself running bish code,
+    // innermost last.
+    suspended: Vec<Suspended>,
+    interrupt: InterruptHandle,
+    // With `--trace`, every instruction run on one of these lines is printed
+    // to stderr along with the stack before and after it.
+    pub trace: Option<RangeInclusive<usize>>,
+    // The traced instruction that is running and the stack before it.
+    traced: Option<(String, String)>
+}
+
+/*
+* What a run needs to carry on where it stopped: kept aside while a native
+* it called compiles and runs other code on the same Vm.
+*/
+struct Suspended {
+    chunk: Chunk,
+    ip: usize,
+    stack: Vec<Value>,
+    const_symbols: Vec<Option<Symbol>>,
+    executed: u64
+}
+
+/*
+* A Rust function bish code can call by name. It gets the Vm it was called
+* from, to read or change globals, and exactly as many arguments as it was
+* registered to take.
+*/
+pub type NativeFn = fn(&mut Vm, &[Value]) -> Result<Value, RuntimeError>;
+
+/*
+* A registered native function. Naming one in bish code without calling it
+* gives a Value::Function, which can be stored and called like the name.
+*/
+#[derive(Debug)]
+pub struct Native {
+    pub name: String,
+    pub arity: usize,
+    pub function: NativeFn,
+    // The group the host must grant before the native can be called.
+    pub capability: Option<Capability>
+}
+
+/*
+* Why compiling or running a program through a Vm failed.
+*/
+#[derive(Clone, Debug)]
+pub enum Error {
+    // The diagnostics have already been printed to stderr.
+    Compile,
+    Runtime(RuntimeError)
+}
+
+impl Error {
+    pub fn report(&self) {
+        match self {
+            Error::Compile => println!("compile error. terminated."),
+            Error::Runtime(error) => error.report()
+        }
+    }
+
+    /*
+    * What the bish command exits with after this error.
+    */
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Compile => 1,
+            Error::Runtime(error) => error.code.number()
+        }
+    }
+}
+
+impl From<RuntimeError> for Error {
+    fn from(error: RuntimeError) -> Error {
+        Error::Runtime(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Compile => write!(f, "compile error"),
+            Error::Runtime(error) => write!(f, "{}[{}]: {}", error.kind, error.code, error.message)
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Vm {
+    /*
+    * A Vm with the standard natives registered but none of their groups
+    * granted.
+    */
+    pub fn new() -> Vm {
+        let mut vm: Vm = Vm {
+            chunk: Chunk::new(),
+            ip: 0,
+            stack: Vec::new(),
+            globals: Vec::new(),
+            symbols: Interner::new(),
+            const_symbols: Vec::new(),
+            natives: HashMap::new(),
+            gc: Heap::new(),
+            output: Box::new(io::stdout()),
+            lints: LintConfig::new(),
+            opt_level: OptLevel::O1,
+            limits: Limits::new(),
+            capabilities: Capabilities::new(),
+            executed: 0,
+            call_depth: 0,
+            suspended: Vec::new(),
+            interrupt: InterruptHandle::default(),
+            trace: None,
+            traced: None
+        };
+        for (capability, name, arity, function) in stdlib::BUILTINS {
+            vm.register_native_in(capability, name, arity, function);
+        }
+        vm
+    }
+
+    /*
+    * Compiles and runs `source`, returning the value of its last top-level
+    * expression statement, if it has one. A native may call this; the
+    * program that called the native carries on once it returns.
+    */
+    pub fn eval(&mut self, source: &str) -> Result<Option<Value>, Error> {
+        self.compile("<eval>", source.to_owned())?;
+        let result: Result<(), Error> = self.run();
+        let value: Option<Value> = self.stack.pop();
+        self.stack.clear();
+        result.map(|()| value)
+    }
+
+    /*
+    * Runs a .bish source file, or a .bishc file without compiling it.
+    */
+    pub fn run_file(&mut self, path: &str) -> Result<(), Error> {
+        if Path::new(path).extension().is_some_and(|ext| ext == bytecode::EXTENSION) {
+            self.load_file(path)?;
+        }
+        else {
+            let source_code: String = match fs::read_to_string(path) {
+                Ok(content) => content,
+                Err(error) => return Err(Error::Runtime(RuntimeError::new(ErrorCode::UnreadableFile, "Error", format!("Tapaile diyeko file '{}' lai padhna sakiyena: {}", path, error))))
+            };
+            self.compile(path, source_code)?;
+        }
+        self.run()
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<&Value> {
+        self.symbols.get(name).and_then(|symbol| self._global(symbol))
+    }
+
+    pub(crate) fn has_native(&self, name: &str) -> bool {
+        self.symbols.get(name).is_some_and(|symbol| self.natives.contains_key(&symbol))
+    }
+
+    /*
+    * Defines `name` for the code run afterwards, or changes it if it exists.
+    */
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        let symbol: Symbol = self.symbols.intern(name);
+        *self._global_slot(symbol) = Some(value);
+    }
+
+    #[inline]
+    fn _global(&self, symbol: Symbol) -> Option<&Value> {
+        self.globals.get(symbol.index()).and_then(Option::as_ref)
+    }
+
+    fn _global_slot(&mut self, symbol: Symbol) -> &mut Option<Value> {
+        if symbol.index() >= self.globals.len() {
+            self.globals.resize(self.symbols.len(), None);
+        }
+        &mut self.globals[symbol.index()]
+    }
+
+    /*
+    * Makes `function` callable from bish code as `name(...)` with `arity`
+    * arguments. Registering a name again replaces the function.
+    */
+    pub fn register_native(&mut self, name: &str, arity: usize, function: NativeFn) {
+        let symbol: Symbol = self.symbols.intern(name);
+        self.natives.insert(symbol, Rc::new(Native { name: name.to_owned(), arity, function, capability: None }));
+    }
+
+    /*
+    * Like register_native, but the function can only be called while
+    * `capability` is granted in `capabilities`.
+    */
+    pub fn register_native_in(&mut self, capability: Capability, name: &str, arity: usize, function: NativeFn) {
+        let symbol: Symbol = self.symbols.intern(name);
+        self.natives.insert(symbol, Rc::new(Native { name: name.to_owned(), arity, function, capability: Some(capability) }));
+    }
+
+    /*
+    * Sends what 'dekhau' prints to `output` instead, returning the writer
+    * used until now.
+    */
+    pub fn set_output(&mut self, output: Box<dyn Write>) -> Box<dyn Write> {
+        std::mem::replace(&mut self.output, output)
+    }
+
+    /*
+    * A handle another thread can use to stop whatever program this Vm is
+    * running.
+    */
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    /*
+    * An array on the Vm's heap, where a collection can free it once it is
+    * only part of a cycle. Natives should make arrays and maps this way.
+    */
+    pub fn new_array(&mut self, values: Vec<Value>) -> Value {
+        self._allocate(Value::array(values))
+    }
+
+    pub fn new_map(&mut self, entries: HashMap<Rc<str>, Value>) -> Value {
+        self._allocate(Value::map(entries))
+    }
+
+    fn _allocate(&mut self, value: Value) -> Value {
+        if self.gc.should_collect() {
+            self.collect_garbage();
+        }
+        self.gc.track(&value);
+        value
+    }
+
+    /*
+    * Frees the arrays and maps that are only reachable from each other,
+    * returning how many there were.
+    */
+    pub fn collect_garbage(&mut self) -> usize {
+        let suspended = self.suspended.iter().flat_map(|suspended| suspended.stack.iter());
+        self.gc.collect(self.stack.iter().chain(suspended).chain(self.globals.iter().flatten()))
+    }
+
+    /*
+    * Every defined global with its name, in no particular order.
+    */
+    pub fn globals(&self) -> impl Iterator<Item = (&str, &Value)> + '_ {
+        self.symbols.names().iter().zip(&self.globals).filter_map(|(name, value)| Some((name.as_str(), value.as_ref()?)))
+    }
+
+    /*
+    * How many instructions the last run executed.
+    */
+    pub fn executed(&self) -> u64 {
+        self.executed
+    }
+
+    /*
+    * The chunk compiled or loaded last.
+    */
+    pub fn chunk(&self) -> &Chunk {
+        &self.chunk
+    }
+
+    /*
+    * What the last program left on the stack, bottom first.
+    */
+    pub fn stack(&self) -> &[Value] {
+        &self.stack
+    }
+
+    /*
+    * Forgets all globals.
+    */
+    pub fn reset(&mut self) {
+        self.globals.iter_mut().for_each(|slot| *slot = None);
+        self.stack.clear();
+    }
+
+    fn stack_push(&mut self, val: Value) {
+        self.stack.push(val);
+    }
+
+    fn stack_pop(&mut self) -> Result<Value, RuntimeError> {
+        match self.stack.pop() {
+            Some(value) => Ok(value),
+            None => Err(RuntimeError::new(ErrorCode::InvalidBytecode, "Internal error", format!("0x{:04x} ma stack khali hunda value nikalna khojiyo.", self.ip - 1)))
+        }
+    }
+
+    fn _format_stack(&self) -> String {
+        let values: Vec<String> = self.stack.iter().map(Value::literal).collect();
+        format!("[{}]", values.join(", "))
+    }
+
+    /*
+    * Compiles `source_code` into the Vm's chunk, replacing what was there.
+    * `source_name` is only used to point diagnostics at the code.
+    */
+    pub fn compile(&mut self, source_name: &str, source_code: String) -> Result<(), Error> {
+        self._suspend_caller();
+        self.chunk = Chunk::new();
+        self.ip = 0;
+        self.stack.clear();
+        let mut program: Vec<ast::Stmt> = match compiler::parse(source_name, source_code) {
+            Some(program) => program,
+            None => return Err(Error::Compile)
+        };
+        let known = self.globals().map(|(name, _)| name).chain(self.natives.values().map(|native| native.name.as_str()));
+        if !lint::Linter::new(source_name, self.lints.clone()).knowing(known).optimizing(self.opt_level == OptLevel::O1).check(&program) {
+            return Err(Error::Compile);
+        }
+        if self.opt_level == OptLevel::O1 {
+            program = optimizer::fold_constants(program);
+        }
+        if !codegen::CodeGen::new(&mut self.chunk, source_name).generate(&program) {
+            return Err(Error::Compile);
+        }
+        if self.opt_level == OptLevel::O1 {
+            optimizer::peephole(&mut self.chunk);
+        }
+        Ok(())
+    }
+
+    /*
+    * Makes the program's name and the arguments after it readable from bish
+    * as `arg0`, `arg1`, ... with their count, not counting `arg0`, in `argc`.
+    */
+    pub fn set_script_args(&mut self, program_name: &str, args: &[String]) {
+        self.set_global("argc", Value::Integer(args.len() as i64));
+        let names = std::iter::once(program_name).chain(args.iter().map(|arg| arg.as_str()));
+        for (idx, arg) in names.enumerate() {
+            self.set_global(&format!("arg{}", idx), Value::from(arg));
+        }
+    }
+
+    /*
+    * Loads a .bishc file in place of the Vm's chunk.
+    */
+    pub fn load_file(&mut self, bytecode_file_path: &str) -> Result<(), Error> {
+        self._suspend_caller();
+        let bytes: Vec<u8> = match fs::read(bytecode_file_path) {
+            Ok(bytes) => bytes,
+            Err(error) => {
+                return Err(Error::Runtime(RuntimeError::new(ErrorCode::UnreadableFile, "Error", format!("Tapaile diyeko file '{}' lai padhna sakiyena: {}", bytecode_file_path, error))));
+            }
+        };
+        let error: RuntimeError = match bytecode::load(&bytes) {
+            Ok(chunk) => {
+                self.chunk = chunk;
+                self.ip = 0;
+                self.stack.clear();
+                return Ok(());
+            },
+            Err(bytecode::LoadError::Corrupt(reason)) => {
+                RuntimeError::new(ErrorCode::CorruptBytecode, "Error", format!("'{}' thik .bishc file hoina: {}.", bytecode_file_path, reason))
+            },
+            Err(bytecode::LoadError::UnsupportedVersion(version)) => {
+                RuntimeError::new(ErrorCode::UnsupportedBytecodeVersion, "Error", format!("'{}' bytecode format version {} ma chha, tara yo bish le version {} matra bujhchha.", bytecode_file_path, version, bytecode::FORMAT_VERSION))
+            }
+        };
+        Err(Error::Runtime(error))
+    }
+
+    /*
+    * Checks the chunk the way run does before running it.
+    */
+    pub fn verify(&self) -> Result<(), Error> {
+        match verifier::verify(&self.chunk) {
+            Ok(()) => Ok(()),
+            Err(error) => {
+                let location: String = match error.line {
+                    Some(line) => format!("0x{:04x} (line {})", error.offset, line),
+                    None => format!("0x{:04x}", error.offset)
+                };
+                Err(Error::Runtime(RuntimeError::new(ErrorCode::InvalidBytecode, "Verification error", format!("bytecode {} ma: {}.", location, error.message))))
+            }
+        }
+    }
+
+    /*
+    * Runs the chunk from the start, after verifying it. An interrupt sent
+    * while nothing was running is forgotten; one sent while a native runs
+    * bish code still stops the program that called it.
+    */
+    pub fn run(&mut self) -> Result<(), Error> {
+        self._suspend_caller();
+        if self.call_depth == 0 {
+            self.interrupt.take();
+        }
+        self.verify()?;
+        self._link();
+        self.ip = 0;
+        self.executed = 0;
+        let started: Instant = Instant::now();
+        let mut next_check: u64 = self._next_check();
+
+        while self.ip < self.chunk.code.len() {
+            let code: u8 = self.chunk.code[self.ip];
+            self.ip += 1;
+            self.executed += 1;
+            if self.executed >= next_check {
+                self._check_instructions(started)?;
+                next_check = self._next_check();
+            }
+            let traced: bool = self.trace.is_some() && self._trace_before();
+            self._interpret_instr(code)?;
+            self._check_stack()?;
+            if traced {
+                self._trace_after();
+            }
+        } 
+        self._check_heap()?;
+        Ok(())
+    }
+
+    /*
+    * With `--trace`, remembers the instruction about to run and the stack
+    * before it when it comes from a traced line, for _trace_after to print.
+    * Both are kept out of line so that untraced runs do not pay for them.
+    */
+    #[cold]
+    #[inline(never)]
+    fn _trace_before(&mut self) -> bool {
+        let offset: usize = self.ip - 1;
+        let traced: bool = self.chunk.lines.get(offset).is_some_and(|line| self.trace.as_ref().is_some_and(|lines| lines.contains(line)));
+        if traced {
+            self.traced = Some((self.chunk.trace_instr(offset), self._format_stack()));
+        }
+        traced
+    }
+
+    #[cold]
+    #[inline(never)]
+    fn _trace_after(&mut self) {
+        if let Some((instr, before)) = self.traced.take() {
+            eprintln!("{:<44} {} -> {}", instr, before, self._format_stack());
+        }
+    }
+
+    /*
+    * The instruction count at which the loop next calls
+    * _check_instructions: the next multiple of CHECK_INTERVAL, or the first
+    * instruction over the limit if that comes sooner. Folding both into one
+    * number leaves a single comparison per instruction.
+    */
+    fn _next_check(&self) -> u64 {
+        let next: u64 = (self.executed / limits::CHECK_INTERVAL + 1) * limits::CHECK_INTERVAL;
+        match self.limits.instructions {
+            Some(max) => next.min(max.saturating_add(1)),
+            None => next
+        }
+    }
+
+    fn _check_instructions(&self, started: Instant) -> Result<(), RuntimeError> {
+        if let Some(max) = self.limits.instructions.filter(|max| self.executed > *max) {
+            return Err(self._limit_error("instructions", max as usize));
+        }
+        if self.executed.is_multiple_of(limits::CHECK_INTERVAL) {
+            self._check_cancelled(started)?;
+            self._check_heap()?;
+        }
+        Ok(())
+    }
+
+    /*
+    * Interns the chunk's string constants, so that equal strings share one
+    * allocation, and resolves the name every global instruction uses to its
+    * slot. This happens once per run rather than on every access, and keeps
+    * symbols out of the bytecode so a .bishc file runs on any Vm.
+    */
+    fn _link(&mut self) {
+        self.const_symbols.clear();
+        for item in self.chunk.const_pool.data.iter_mut() {
+            let symbol: Option<Symbol> = match &item.data {
+                Value::String(text) => {
+                    let symbol: Symbol = self.symbols.intern(text);
+                    item.data = Value::String(self.symbols.name(symbol).clone());
+                    Some(symbol)
+                },
+                _ => None
+            };
+            self.const_symbols.push(symbol);
+        }
+        self.globals.resize(self.symbols.len(), None);
+    }
+
+    /*
+    * The slot named by constant `index`; the verifier has checked that it
+    * is a string.
+    */
+    #[inline]
+    fn _symbol(&self, index: u16) -> Result<Symbol, RuntimeError> {
+        match self.const_symbols.get(index as usize) {
+            Some(Some(symbol)) => Ok(*symbol),
+            _ => Err(RuntimeError::new(ErrorCode::InvalidBytecode, "Internal error", format!("constant {} global ko naam hoina.", index)))
+        }
+    }
+
+    /*
+    * The stack grows by at most one value per instruction, so checking after
+    * each one is enough.
+    */
+    fn _check_stack(&self) -> Result<(), RuntimeError> {
+        match self.limits.stack {
+            Some(max) if self.stack.len() > max => Err(self._limit_error("stack", max)),
+            _ => Ok(())
+        }
+    }
+
+    /*
+    * heap_bytes walks everything reachable, so it only runs every
+    * CHECK_INTERVAL instructions and once more when the program ends. A
+    * program can go over the limit by what its native calls allocate in
+    * between before it is stopped.
+    */
+    fn _check_heap(&self) -> Result<(), RuntimeError> {
+        match self.limits.heap_bytes {
+            Some(max) if self.heap_bytes() > max => Err(self._limit_error("heap_bytes", max)),
+            _ => Ok(())
+        }
+    }
+
+    /*
+    * Bytes of heap data reachable from globals and the stack: global names,
+    * strings and the slots of arrays and maps. Data shared by several values
+    * is counted once.
+    */
+    pub fn heap_bytes(&self) -> usize {
+        let mut seen: HashSet<*const ()> = HashSet::new();
+        let names: usize = self.globals().map(|(name, _)| name.len()).sum();
+        let suspended = self.suspended.iter().flat_map(|suspended| suspended.stack.iter());
+        names + self.globals.iter().flatten().chain(self.stack.iter()).chain(suspended).map(|value| Vm::_heap_size(value, &mut seen)).sum::<usize>()
+    }
+
+    fn _heap_size(value: &Value, seen: &mut HashSet<*const ()>) -> usize {
+        match value {
+            Value::String(text) if seen.insert(Rc::as_ptr(text) as *const ()) => text.len(),
+            Value::Array(values) if seen.insert(Rc::as_ptr(values) as *const ()) => {
+                let values = values.borrow();
+                values.len() * mem::size_of::<Value>() + values.iter().map(|value| Vm::_heap_size(value, seen)).sum::<usize>()
+            },
+            Value::Map(entries) if seen.insert(Rc::as_ptr(entries) as *const ()) => {
+                let entries = entries.borrow();
+                entries.iter().map(|(key, value)| key.len() + mem::size_of::<Value>() + Vm::_heap_size(value, seen)).sum()
+            },
+            _ => 0
+        }
+    }
+
+    fn _check_cancelled(&self, started: Instant) -> Result<(), RuntimeError> {
+        if self.interrupt.take() {
+            return Err(RuntimeError::new(ErrorCode::Cancelled, "Cancelled", String::from("program lai bichmai rokiyo (script cancelled).")));
+        }
+        match self.limits.timeout {
+            Some(timeout) if started.elapsed() > timeout => {
+                Err(RuntimeError::new(ErrorCode::Cancelled, "Cancelled", format!("program le {:?} bhanda badhi samay lagayo, tesaile rokiyo (script cancelled).", timeout)))
+            },
+            _ => Ok(())
+        }
+    }
+
+    fn _limit_error(&self, limit: &str, max: usize) -> RuntimeError {
+        RuntimeError::new(ErrorCode::LimitExceeded, "Limit error", format!("program le '{}' ko seema ({}) nageko chha, tesaile rokiyo.", limit, max))
+    }
+
+    fn _interpret_instr(&mut self, i: u8) -> Result<(), RuntimeError> {
+        let instr: OpCode = match OpCode::from_u8(i) {
+            Some(instr) => instr,
+            None => return Err(RuntimeError::new(ErrorCode::InvalidBytecode, "Internal error", format!("0x{:04x} ma {} kunai opcode hoina.", self.ip - 1, i)))
+        };
+        match instr {
+            OpCode::OP_RETURN => { },
+            OpCode::OP_NOP => (),
+            OpCode::OP_CONST => {
+                let index: u16 = self._read_short_from_chunk();
+                let con: Value = self.chunk.read_const(index);
+                match con {
+                    Value::Unknown => {
+                        return Err(RuntimeError::new(ErrorCode::UnknownConstant, "Internal error", String::from("constant pool ma ajnat prakar ko value bhetiyo.")));
+                    },
+                    _ => self.stack_push(con)
+                }
+            },
+            OpCode::OP_TRUE => self.stack_push(Value::Boolean(true)),
+            OpCode::OP_FALSE => self.stack_push(Value::Boolean(false)),
+            OpCode::OP_NIL => self.stack_push(Value::Nil),
+            OpCode::OP_AND | 
+            OpCode::OP_OR | 
+            OpCode::OP_ADD | 
+            OpCode::OP_SUBTRACT | 
+            OpCode::OP_MULTIPLY |
+            OpCode::OP_DIVIDE | 
+            OpCode::OP_EQ_EQ |
+            OpCode::OP_GT |
+            OpCode::OP_LT => self._interpret_binary_instr(instr)?,
+            OpCode::OP_NEGATE => self._perform_negate_op()?,
+            OpCode::OP_NOT => self._perform_not_op()?,
+            OpCode::OP_PRINT => self._interpret_print_stmt()?,
+            OpCode::OP_POP => { self.stack_pop()?; },
+            OpCode::OP_DEF_GLOBAL => {
+                let index: u16 = self._read_short_from_chunk();
+                let name: Symbol = self._symbol(index)?;
+                let value: Value = self.stack_pop()?;
+                self._define_global_var(name, value);
+            },
+            OpCode::OP_LOAD_GLOBAL => {
+                let index: u16 = self._read_short_from_chunk();
+                let name: Symbol = self._symbol(index)?;
+                self._load_global_into_stack(name)?;
+            },
+            OpCode::OP_SET_GLOBAL => {
+                let index: u16 = self._read_short_from_chunk();
+                let name: Symbol = self._symbol(index)?;
+                let value: Value = self.stack_pop()?;
+                self._set_global_var(name, value)?;
+            },
+            OpCode::OP_JMP_IF_FALSE => {
+                /*
+                * OP_JMP_IF_FALSE
+                * OFF_1
+                * OFF_2
+                * ...   <--- IP, jump offsets are counted from here
+                */
+                let offset: u16 = self._read_short_from_chunk();
+                let condition: Value = self.stack_pop()?;
+                if let Value::Boolean(false) = condition {
+                    self.ip += offset as usize;
+                }
+            },
+            OpCode::OP_ELSE => {
+                self._perform_else_op();
+            },
+            OpCode::OP_LOOP => {
+                let offset: u16 = self._read_short_from_chunk();
+                self.ip -= offset as usize;
+            },
+            OpCode::OP_CALL => {
+                let index: u16 = self._read_short_from_chunk();
+                let argc: usize = self.chunk.code[self.ip] as usize;
+                self.ip += 1;
+                let name: Symbol = self._symbol(index)?;
+                self._call_native(name, argc)?;
+            },
+            _ => ()
+        }
+        Ok(())
+    }
+
+    /*
+    * Calls the native registered as `name`, or the function value held by
+    * the global `name`.
+    */
+    fn _call_native(&mut self, symbol: Symbol, argc: usize) -> Result<(), RuntimeError> {
+        let name: Rc<String> = self.symbols.name(symbol).clone();
+        let native: Rc<Native> = match (self._global(symbol), self.natives.get(&symbol)) {
+            (Some(Value::Function(native)), _) | (None, Some(native)) => native.clone(),
+            (Some(value), native) => {
+                let mut message: String = format!("'{}' function hoina, yo {} ho, tesaile yeslai call garna mildaina.", name, Value::name(value));
+                if native.is_some() {
+                    message.push_str(&format!("\n  = madat: '{}' naam ko global le usai naam ko native function lai dhakeko chha.", name));
+                }
+                return Err(RuntimeError::new(ErrorCode::InvalidCall, "Runtime error", message));
+            },
+            (None, None) => {
+                let mut message: String = format!("'{}' bhanne function chhaina.", name);
+                if let Some(known) = diagnostics::suggest(&name, self.natives.values().map(|native| native.name.as_str())) {
+                    message.push_str(&format!("\n  = madat: {}", diagnostics::did_you_mean(known)));
+                }
+                return Err(RuntimeError::new(ErrorCode::UndefinedFunction, "Runtime error", message));
+            }
+        };
+        if let Some(capability) = native.capability.filter(|capability| !self.capabilities.allows(*capability)) {
+            return Err(RuntimeError::new(ErrorCode::CapabilityDenied, "Runtime error", format!("'{}' chalauna '{}' ko anumati chahinchha, tara yo program lai diyieko chhaina.", name, capability)));
+        }
+        if argc != native.arity {
+            return Err(RuntimeError::new(ErrorCode::WrongArgumentCount, "Runtime error", format!("'{}' le {} wata argument linchha, tara {} wata diyiyo.", name, native.arity, argc)));
+        }
+        if self.limits.call_depth.is_some_and(|max| self.call_depth >= max) {
+            return Err(self._limit_error("call_depth", self.limits.call_depth.unwrap()));
+        }
+        let args: Vec<Value> = self.stack.split_off(self.stack.len() - argc);
+        self.call_depth += 1;
+        let result: Result<Value, RuntimeError> = (native.function)(self, &args);
+        self.call_depth -= 1;
+        if self.suspended.len() > self.call_depth {
+            self._resume_caller();
+        }
+        match result {
+            Ok(value) => {
+                self.stack_push(value);
+                Ok(())
+            },
+            Err(mut error) => {
+                if error.code == ErrorCode::NativeFunctionError {
+                    error.message = format!("'{}': {}", name, error.message);
+                }
+                Err(error)
+            }
+        }
+    }
+
+    /*
+    * Called where a new program takes over the chunk. Inside a native, the
+    * program that called it is still running, so its state is put aside
+    * for _call_native to bring back once the native returns; each native
+    * call sets aside at most one.
+    */
+    fn _suspend_caller(&mut self) {
+        if self.suspended.len() < self.call_depth {
+            self.suspended.push(Suspended {
+                chunk: mem::replace(&mut self.chunk, Chunk::new()),
+                ip: self.ip,
+                stack: mem::take(&mut self.stack),
+                const_symbols: mem::take(&mut self.const_symbols),
+                executed: self.executed
+            });
+        }
+    }
+
+    fn _resume_caller(&mut self) {
+        if let Some(suspended) = self.suspended.pop() {
+            self.chunk = suspended.chunk;
+            self.ip = suspended.ip;
+            self.stack = suspended.stack;
+            self.const_symbols = suspended.const_symbols;
+            self.executed = suspended.executed;
+        }
+    }
+
+    fn _perform_else_op(&mut self) {
+        let offset: u16 = self._read_short_from_chunk();
+        self.ip += offset as usize;
+    }
+
+    /*
+    * A name that is not a global but is a native loads the native as a
+    * function value.
+    */
+    fn _load_global_into_stack(&mut self, var_name: Symbol) -> Result<(), RuntimeError> {
+        if let Some(_val) = self._global(var_name) {
+            self.stack_push(_val.clone());
+        }
+        else if let Some(native) = self.natives.get(&var_name) {
+            self.stack_push(Value::Function(native.clone()));
+        }
+        else {
+            return Err(self._undefined_var_error(self.symbols.name(var_name)));
+        }
+        Ok(())
+    }
+
+    fn _define_global_var(&mut self, name: Symbol, value: Value) {
+        *self._global_slot(name) = Some(value);
+    }
+
+    fn _set_global_var(&mut self, name: Symbol, value: Value) -> Result<(), RuntimeError> {
+        match self._global_slot(name) {
+            Some(slot) => *slot = value,
+            None => return Err(self._undefined_var_error(self.symbols.name(name)))
+        }
+        Ok(())
+    }
+
+    fn _undefined_var_error(&self, name: &str) -> RuntimeError {
+        let mut message: String = format!("'{}' bhanne variable pahile banaiyeko chhaina. Kripaya variable use garnu bhanda agadi teslai banaunu hola.", name);
+        let known = self.globals().map(|(name, _)| name);
+        let keywords = scanner::KEYWORDS.iter().map(|(keyword, _)| *keyword);
+        let suggestion: Option<&str> = diagnostics::suggest(name, known).or_else(|| diagnostics::suggest(name, keywords));
+        if let Some(name) = suggestion {
+            message.push_str(&format!("\n  = madat: {}", diagnostics::did_you_mean(name)));
+        }
+        RuntimeError::new(ErrorCode::UndefinedVariable, "Runtime error", message)
+    }
+
+    fn _interpret_print_stmt(&mut self) -> Result<(), RuntimeError> {
+        let value: &Value = &self.stack_pop()?;
+        let written: io::Result<()> = match value {
+            Value::String(value) => writeln!(self.output, "{}", value),
+            Value::Double(value) => writeln!(self.output, "{}", value),
+            Value::Integer(value) => writeln!(self.output, "{}", value),
+            Value::Boolean(value) => writeln!(self.output, "{}", if *value { "sahi" } else { "galat" }),
+            Value::Nil => writeln!(self.output, "nil"),
+            Value::Array(_) | Value::Map(_) | Value::Function(_) => writeln!(self.output, "{}", Value::literal(value)),
+            _ => {
+                return Err(RuntimeError::new(ErrorCode::UnprintableValue, "Runtime error", format!("'{}' prakar ko value dekhauna sakiyena.", Value::name(value))));
+            }
+        };
+        match written {
+            Ok(()) => Ok(()),
+            Err(error) => Err(RuntimeError::new(ErrorCode::UnwritableFile, "Runtime error", format!("'dekhau' ko output lekhna sakiyena: {}", error)))
+        }
+    }
+    
+    fn _interpret_binary_instr(&mut self, instr: OpCode) -> Result<(), RuntimeError> {
+        let aa: &Value = &self.stack_pop()?;
+        let bb: &Value = &self.stack_pop()?;
+        let mut ok: bool = true;
+
+        match instr {
+            OpCode::OP_AND | OpCode::OP_OR => {
+                let avalue = match aa {
+                    Value::Integer(value) => *value,
+                    _ => {
+                        ok = false;
+                        0
+                    }
+                };
+                let bvalue = match bb {
+                    Value::Integer(value) => *value,
+                    _ => {
+                        ok = false;
+                        0
+                    }
+                };
+
+                match ok {
+                    true => self._perform_logical_op(instr, avalue, bvalue),
+                    false => { 
+                        return Err(RuntimeError::new(ErrorCode::LogicalOperandType, "Type error", format!(
+                            "Unsupported types for '{}' operation for types: '{:?}' and '{:?}'", 
+                            if instr == OpCode::OP_AND { "ra(&&)" } 
+                            else if instr == OpCode::OP_OR { "wa(||)" } 
+                            else { "unknown" }, 
+                            aa, 
+                            bb
+                        )));
+                    }
+                }
+            },
+            OpCode::OP_ADD |
+            OpCode::OP_SUBTRACT |
+            OpCode::OP_MULTIPLY |
+            OpCode::OP_DIVIDE => {
+                let mut avalue_double: bool = false;
+                let mut bvalue_double: bool = false;
+                let mut avalue_f: f64 = 0.0;
+                let mut bvalue_f: f64 = 0.0;
+                let mut avalue_i: i64 = 0;
+                let mut bvalue_i: i64 = 0;
+                match aa {
+                    Value::Double(value) => {
+                        avalue_double = true;
+                        avalue_f = *value;
+                    },
+                    Value::Integer(value) => avalue_i = *value,
+                    _ => {}
+                };
+
+                match bb {
+                    Value::Double(value) => {
+                        bvalue_double = true;
+                        bvalue_f = *value;
+                    },
+                    Value::Integer(value) => bvalue_i = *value,
+                    _ => {}
+                };
+
+                match (avalue_double, bvalue_double) {
+                    (true, true) => self._perform_arithmetic_op_double(instr, avalue_f, bvalue_f),
+                    (true, false) => self._perform_arithmetic_op_double(instr, avalue_f, bvalue_i as f64),
+                    (false, true) => self._perform_arithmetic_op_double(instr, avalue_i as f64, bvalue_f),
+                    (false, false) => self._perform_arithmetic_op_int(instr, avalue_i, bvalue_i)?
+                }
+            },
+            OpCode::OP_LT |
+            OpCode::OP_GT |
+            OpCode::OP_EQ_EQ => {
+                self._perform_relational_op(aa, bb, instr)?;
+            },
+            _ => ()
+        }
+        Ok(())
+    }
+
+    fn _perform_not_op(&mut self) -> Result<(), RuntimeError> {
+        let value: &Value = &self.stack_pop()?;
+        match value {
+            Value::Integer(value) => self.stack_push(Value::Boolean(*value == 0)),
+            Value::Boolean(cond) => self.stack_push(Value::Boolean(!cond)),
+            _ => {
+                return Err(RuntimeError::new(ErrorCode::NotOperandType, "Type error", format!("can't apply 'chhaina' operator on type '{}'", Value::name(value))));
+            }
+        }
+        Ok(())
+    }
+
+    fn _perform_negate_op(&mut self) -> Result<(), RuntimeError> {
+        let value: &Value = &self.stack_pop()?;
+        match value {
+            Value::Integer(value) => match value.checked_neg() {
+                Some(negated) => self.stack_push(Value::Integer(negated)),
+                None => return Err(self.overflow_error(&format!("-({})", value)))
+            },
+            Value::Double(value) => self.stack_push(Value::Double(-*value)),
+            _ => {
+                return Err(RuntimeError::new(ErrorCode::NegateOperandType, "Type error", format!("can't apply negate(-) operator on type '{}'", Value::name(value))));
+            }
+        }
+        Ok(())
+    }
+
+    fn _perform_relational_op(&mut self, val1: &Value, val2: &Value, instr: OpCode) -> Result<(), RuntimeError> {
+        let result: bool = match instr {
+            OpCode::OP_GT => self._relational_op_gt(val1, val2)?,
+            OpCode::OP_LT => self._relational_op_lt(val1, val2)?,
+            OpCode::OP_EQ_EQ => self._relational_op_eq_eq(val1, val2)?,
+            _ => false
+        };
+        self.stack_push(Value::Boolean(result));
+        Ok(())
+    }
+
+    /*
+    * Strings that both came from the program's constants are interned and
+    * compare by address. Strings from natives or the host are compared by
+    * their text.
+    */
+    fn _relational_op_eq_eq(&mut self, val1: &Value, val2: &Value) -> Result<bool, RuntimeError> {
+        match (val1, val2) {
+            (Value::String(a), Value::String(b)) if self.symbols.is_interned(a) && self.symbols.is_interned(b) => Ok(Rc::ptr_eq(a, b)),
+            (Value::Integer(a), Value::Integer(b)) => Ok(a == b),
+            (Value::Double(a), Value::Double(b)) => Ok(a == b),
+            (Value::Boolean(cond1), Value::Boolean(cond2)) => Ok(cond1 == cond2),
+            (Value::String(_), Value::String(_)) | (Value::Array(_), Value::Array(_)) | (Value::Map(_), Value::Map(_)) | (Value::Function(_), Value::Function(_)) => Ok(val1 == val2),
+            _ => Err(self.type_error("barabar", &Value::name(val1), &Value::name(val2)))
+        }
+    }
+
+    fn _relational_op_gt(&mut self, val1: &Value, val2: &Value) -> Result<bool, RuntimeError> {
+        match (val1, val2) {
+            (Value::Integer(a), Value::Integer(b)) => Ok(b > a),
+            (Value::Double(a), Value::Double(b)) => Ok(b > a),
+            _ => Err(self.type_error("thulo", &Value::name(val1), &Value::name(val2)))
+        }
+    }
+
+    fn _relational_op_lt(&mut self, val1: &Value, val2: &Value) -> Result<bool, RuntimeError> {
+        match (val1, val2) {
+            (Value::Integer(a), Value::Integer(b)) => Ok(b < a),
+            (Value::Double(a), Value::Double(b)) => Ok(b < a),
+            _ => Err(self.type_error("sano", &Value::name(val1), &Value::name(val2)))
+        }
+    }
+
+    fn _perform_logical_op(&mut self, instr: OpCode, avalue: i64, bvalue: i64) {
+        match instr {
+            OpCode::OP_AND => self.stack_push(Value::Integer(avalue & bvalue)),
+            OpCode::OP_OR => self.stack_push(Value::Integer(avalue | bvalue)),
+            _ => ()
+        }
+    }
+
+    fn _perform_arithmetic_op_double(&mut self, instr: OpCode, avalue: f64, bvalue: f64) {
+        match instr {
+            OpCode::OP_ADD => self.stack_push(Value::Double(avalue + bvalue)),
+            OpCode::OP_SUBTRACT => self.stack_push(Value::Double(bvalue - avalue)),
+            OpCode::OP_DIVIDE => self.stack_push(Value::Double(bvalue / avalue)),
+            OpCode::OP_MULTIPLY => self.stack_push(Value::Double(bvalue * avalue)),
+            _ => ()
+        }
+    }
+    
+    fn _perform_arithmetic_op_int(&mut self, instr: OpCode, avalue: i64, bvalue: i64) -> Result<(), RuntimeError> {
+        let (result, op): (Option<i64>, &str) = match instr {
+            OpCode::OP_ADD => (bvalue.checked_add(avalue), "+"),
+            OpCode::OP_SUBTRACT => (bvalue.checked_sub(avalue), "-"),
+            OpCode::OP_DIVIDE if avalue == 0 => {
+                return Err(RuntimeError::new(ErrorCode::DivisionByZero, "Runtime error", format!("'{}' lai 0 le bhag garna mildaina.", bvalue)));
+            },
+            OpCode::OP_DIVIDE => (Some((bvalue as f64 / avalue as f64) as i64), "/"),
+            OpCode::OP_MULTIPLY => (bvalue.checked_mul(avalue), "*"),
+            _ => return Ok(())
+        };
+        match result {
+            Some(result) => {
+                self.stack_push(Value::Integer(result));
+                Ok(())
+            },
+            None => Err(self.overflow_error(&format!("{} {} {}", bvalue, op, avalue)))
+        }
+    }
+
+    fn overflow_error(&self, expression: &str) -> RuntimeError {
+        RuntimeError::new(ErrorCode::IntegerOverflow, "Runtime error", format!("'{}' ko natija 64 bit int ma atauna sakdaina.", expression))
+    }
+
+    fn type_error(&self, op: &str, type1: &str, type2: &str) -> RuntimeError {
+        RuntimeError::new(ErrorCode::BinaryOperandType, "Type error", format!("'{}' ra '{}' prakar ko value harulai '{}' operator lagauna mildaina.", type1, type2, op))
+    }
+
+    fn _read_short_from_chunk(&mut self) -> u16 {
+        let value: u16 = self.chunk.read_short(self.ip);
+        self.ip += 2;
+        value
+    }
+}
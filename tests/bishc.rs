@@ -0,0 +1,161 @@
+// MIT License
+//
+// Copyright (c) 2023 Ramesh Poudel
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+/*
+* Round-trips programs through `bish build` and `bish run` on the .bishc
+* file, and checks that damaged files are turned away with the right code.
+*/
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn bish(args: &[&Path]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_bish"))
+        .args(args)
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("bish binary should start")
+}
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir: PathBuf = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn built_program_runs_like_source() {
+    let dir: PathBuf = scratch_dir("bishc_round_trip");
+    let source: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/folding.bish");
+    let compiled: PathBuf = dir.join("folding.bishc");
+
+    let build: Output = bish(&[Path::new("build"), &source, Path::new("-o"), &compiled]);
+    assert_eq!(build.status.code(), Some(0), "{}", String::from_utf8_lossy(&build.stderr));
+
+    let from_source: Output = bish(&[Path::new("run"), &source]);
+    let from_bishc: Output = bish(&[Path::new("run"), &compiled]);
+    assert_eq!(from_bishc.status.code(), Some(0), "{}", String::from_utf8_lossy(&from_bishc.stderr));
+    assert_eq!(from_source.stdout, from_bishc.stdout);
+    assert!(from_bishc.stderr.is_empty(), "running a .bishc file should not repeat compile warnings");
+}
+
+#[test]
+fn damaged_files_are_rejected() {
+    let dir: PathBuf = scratch_dir("bishc_damaged");
+    let source: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/loop.bish");
+    let compiled: PathBuf = dir.join("loop.bishc");
+    assert_eq!(bish(&[Path::new("build"), &source, Path::new("-o"), &compiled]).status.code(), Some(0));
+    let good: Vec<u8> = fs::read(&compiled).unwrap();
+
+    let mut flipped: Vec<u8> = good.clone();
+    let middle: usize = flipped.len() / 2;
+    flipped[middle] ^= 0xFF;
+    let mut newer: Vec<u8> = good.clone();
+    newer[5] += 1;
+    let cases: [(&str, Vec<u8>, i32); 5] = [
+        ("empty", Vec::new(), 29),
+        ("not_bishc", b"dekhau 1;".to_vec(), 29),
+        ("truncated", good[..good.len() / 2].to_vec(), 29),
+        ("flipped", flipped, 29),
+        ("newer_version", newer, 30)
+    ];
+    for (name, bytes, code) in cases {
+        let path: PathBuf = dir.join(format!("{}.bishc", name));
+        fs::write(&path, bytes).unwrap();
+        let output: Output = bish(&[Path::new("run"), &path]);
+        assert_eq!(output.status.code(), Some(code), "{}: {}", name, String::from_utf8_lossy(&output.stderr));
+        assert!(output.stdout.is_empty(), "{}: nothing should run", name);
+    }
+}
+
+/*
+* A .bishc file holding `code` with a single integer constant, one line per
+* byte, as `bish build` would lay it out.
+*/
+fn hand_built(code: &[u8]) -> Vec<u8> {
+    let mut out: Vec<u8> = b"BSHC".to_vec();
+    out.extend_from_slice(&1u16.to_be_bytes());
+    out.extend_from_slice(&1u32.to_be_bytes());
+    out.push(0);
+    out.extend_from_slice(&7i64.to_be_bytes());
+    out.extend_from_slice(&(code.len() as u32).to_be_bytes());
+    out.extend_from_slice(code);
+    out.extend_from_slice(&(code.len() as u32).to_be_bytes());
+    for line in 1..=code.len() as u32 {
+        out.extend_from_slice(&line.to_be_bytes());
+        out.extend_from_slice(&1u32.to_be_bytes());
+    }
+    let mut hash: u32 = 0x811c9dc5;
+    for byte in &out {
+        hash ^= *byte as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    out.extend_from_slice(&hash.to_be_bytes());
+    out
+}
+
+#[test]
+fn invalid_bytecode_is_not_run() {
+    let dir: PathBuf = scratch_dir("bishc_invalid");
+    // OP_CONST 0, OP_PRINT is fine; each case breaks it one way.
+    let valid: &[u8] = &[1, 0, 0, 19];
+    let cases: [(&str, &[u8], &str); 9] = [
+        ("unknown_opcode", &[1, 0, 0, 99, 19], "0x0003 (line 4)"),
+        ("unsupported_opcode", &[1, 0, 0, 1, 0, 0, 15, 19], "OP_LTE"),
+        ("truncated_operand", &[1, 0], "0x0000 (line 1)"),
+        ("missing_constant", &[1, 0, 5, 19], "constant 5"),
+        ("jump_outside_code", &[24, 0, 9, 1, 0, 0, 19], "OP_ELSE"),
+        ("jump_into_operand", &[24, 0, 1, 1, 0, 0, 19], "0x0004"),
+        ("stack_underflow", &[1, 0, 0, 4, 19], "OP_ADD"),
+        ("call_without_a_name", &[1, 0, 0, 27, 0, 0, 1, 19], "OP_CALL ko constant 0"),
+        ("truncated_call", &[1, 0, 0, 27, 0, 0], "OP_CALL ko operand")
+    ];
+    let path: PathBuf = dir.join("valid.bishc");
+    fs::write(&path, hand_built(valid)).unwrap();
+    let output: Output = bish(&[Path::new("run"), &path]);
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "7\n");
+
+    for (name, code, detail) in cases {
+        let path: PathBuf = dir.join(format!("{}.bishc", name));
+        fs::write(&path, hand_built(code)).unwrap();
+        let output: Output = bish(&[Path::new("run"), &path]);
+        let stderr: String = String::from_utf8_lossy(&output.stderr).into_owned();
+        assert_eq!(output.status.code(), Some(32), "{}: {}", name, stderr);
+        assert!(stderr.contains(detail), "{}: expected '{}' in {}", name, detail, stderr);
+        assert!(output.stdout.is_empty(), "{}: nothing should run", name);
+    }
+}
+
+#[test]
+fn unbalanced_branches_are_not_run() {
+    let dir: PathBuf = scratch_dir("bishc_unbalanced");
+    // OP_TRUE, OP_JMP_IF_FALSE +3, OP_CONST 0, then both paths meet at OP_PRINT
+    // with one and zero values on the stack.
+    let path: PathBuf = dir.join("unbalanced.bishc");
+    fs::write(&path, hand_built(&[9, 23, 0, 3, 1, 0, 0, 19])).unwrap();
+    let output: Output = bish(&[Path::new("run"), &path]);
+    let stderr: String = String::from_utf8_lossy(&output.stderr).into_owned();
+    assert_eq!(output.status.code(), Some(32), "{}", stderr);
+    assert!(stderr.contains("0x0007 (line 8)"), "{}", stderr);
+}
@@ -0,0 +1,184 @@
+// MIT License
+//
+// Copyright (c) 2023 Ramesh Poudel
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+/*
+* The subcommands and the options they share: inline code with `-e`,
+* programs on stdin with `-`, the arguments passed through to the program
+* and the exit codes.
+*/
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+fn bish(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_bish"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .env("NO_COLOR", "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("bish binary should start");
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir: PathBuf = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn inline_code() {
+    let output: Output = bish(&["-e", "dekhau 1 + 2;"], "");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn program_on_stdin() {
+    let output: Output = bish(&["-"], "rakha x ma 4;\ndekhau x * x;\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "16\n");
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn arguments_after_the_program_are_passed_to_it() {
+    let output: Output = bish(&["-e", "dekhau argc; dekhau arg0; dekhau arg1; dekhau arg2;", "--trace", "pachhi"], "");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "2\n<-e>\n--trace\npachhi\n");
+    assert!(output.stderr.is_empty(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let output: Output = bish(&["-", "ek"], "dekhau arg0; dekhau arg1;");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "<stdin>\nek\n");
+}
+
+#[test]
+fn build_from_inline_code_needs_an_output_path() {
+    let output: Output = bish(&["build", "-e", "dekhau 1;"], "");
+    assert_eq!(output.status.code(), Some(12));
+}
+
+#[test]
+fn stack_is_only_dumped_on_request() {
+    let output: Output = bish(&["-e", "1 + 2;"], "");
+    assert!(output.stdout.is_empty(), "{}", String::from_utf8_lossy(&output.stdout));
+
+    let output: Output = bish(&["run", "--dump-stack", "-e", "1 + 2;"], "");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "[3]\n");
+}
+
+#[test]
+fn check_compiles_without_running() {
+    let output: Output = bish(&["check", "-e", "dekhau 1;", "tests/golden/loop.bish"], "");
+    assert!(output.stdout.is_empty(), "{}", String::from_utf8_lossy(&output.stdout));
+    assert_eq!(output.status.code(), Some(0));
+
+    let output: Output = bish(&["check", "tests/golden/loop.bish", "tests/golden/missing_semicolon.bish"], "");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("B0020"));
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn fmt_rewrites_files_and_checks_them() {
+    let dir: PathBuf = scratch_dir("fmt");
+    let program: PathBuf = dir.join("program.bish");
+    fs::write(&program, "rakha x ma (1+2)*3;\nyadi x thulo 5 { dekhau x; }\n\n\nnatra_pachhi ma chhaina sahi;").unwrap();
+    let path: &str = program.to_str().unwrap();
+
+    let output: Output = bish(&["fmt", "--check", path], "");
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), path);
+    assert_eq!(output.status.code(), Some(1));
+
+    assert_eq!(bish(&["fmt", path], "").status.code(), Some(0));
+    assert_eq!(fs::read_to_string(&program).unwrap(), "\
+rakha x ma (1 + 2) * 3;
+yadi x thulo 5 {
+    dekhau x;
+}
+
+natra_pachhi ma chhaina sahi;
+");
+    assert_eq!(bish(&["fmt", "--check", path], "").status.code(), Some(0));
+
+    let output: Output = bish(&["fmt", "-"], "ghumau sahi{patak;}");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ghumau sahi {\n    patak;\n}\n");
+}
+
+#[test]
+fn test_runs_every_program_under_a_directory() {
+    let dir: PathBuf = scratch_dir("test_subcommand");
+    fs::write(dir.join("passes.bish"), "dekhau 1 + 1;").unwrap();
+    fs::write(dir.join("passes.out"), "2\n").unwrap();
+    fs::write(dir.join("wrong_output.bish"), "dekhau 3;").unwrap();
+    fs::write(dir.join("wrong_output.out"), "4\n").unwrap();
+    fs::write(dir.join("no_output_file.bish"), "rakha x ma 1;").unwrap();
+    fs::write(dir.join("runtime_error.bish"), "dekhau y;").unwrap();
+
+    let output: Output = bish(&["test", dir.to_str().unwrap()], "");
+    let stdout: String = String::from_utf8_lossy(&output.stdout).into_owned();
+    assert!(stdout.contains("no_output_file.bish ... ok"), "{}", stdout);
+    assert!(stdout.contains("passes.bish ... ok"), "{}", stdout);
+    assert!(stdout.contains("runtime_error.bish ... FAILED\nexit code 18"), "{}", stdout);
+    assert!(stdout.contains("wrong_output.bish ... FAILED"), "{}", stdout);
+    assert!(stdout.ends_with("test result: 2 pass, 2 fail\n"), "{}", stdout);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn limits_are_set_on_the_command_line() {
+    let output: Output = bish(&["run", "--limit", "instructions=500", "-e", "ghumau sahi { }"], "");
+    assert_eq!(output.status.code(), Some(38), "{}", String::from_utf8_lossy(&output.stderr));
+    let deep: String = format!("rakha x ma 1; {}x{};", "x + (".repeat(300), ")".repeat(300));
+    assert_eq!(bish(&["run", "-e", &deep], "").status.code(), Some(38));
+    assert_eq!(bish(&["run", "--limit", "stack=none", "-e", &deep], "").status.code(), Some(0));
+    assert_eq!(bish(&["run", "--limit", "timeout_ms=100", "-e", "ghumau sahi { }"], "").status.code(), Some(39));
+    assert_eq!(bish(&["run", "--limit", "ghanta=1", "-e", "dekhau 1;"], "").status.code(), Some(33));
+}
+
+#[test]
+fn capabilities_are_granted_on_the_command_line() {
+    let program: &str = "dekhau samay() thulo 0.0;";
+    assert_eq!(bish(&["-e", program], "").status.code(), Some(40));
+    let output: Output = bish(&["run", "--grant", "clock", "-e", program], "");
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(bish(&["run", "--grant", "all", "-e", program], "").status.code(), Some(0));
+    assert_eq!(bish(&["run", "--grant", "network", "-e", program], "").status.code(), Some(33));
+}
+
+#[test]
+fn options_a_subcommand_does_not_take_are_rejected() {
+    for args in [&["build", "--trace", "-e", "dekhau 1;"][..], &["check", "--frobnicate", "x.bish"], &["fmt", "-O0", "x.bish"], &["build", "--gc-stress", "x.bish"], &["run", "--deny"]] {
+        let output: Output = bish(args, "");
+        assert_eq!(output.status.code(), Some(33), "{:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+    }
+    assert_eq!(bish(&["disasm"], "").status.code(), Some(12));
+    assert_eq!(bish(&["--explain"], "").status.code(), Some(33));
+    assert_eq!(bish(&["--explain", "B9999"], "").status.code(), Some(33));
+    assert_eq!(bish(&["--explain", "B0033"], "").status.code(), Some(0));
+    assert_eq!(bish(&["--help"], "").status.code(), Some(0));
+}
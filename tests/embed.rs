@@ -0,0 +1,533 @@
+// MIT License
+//
+// Copyright (c) 2023 Ramesh Poudel
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+
+/*
+* Uses bish as a library, the way a Rust program embedding it would.
+*/
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
+
+use bish::ast::Stmt;
+use bish::diagnostics::ErrorCode;
+use bish::{bytecode, compiler, formatter, Capabilities, Capability, Chunk, Error, InterruptHandle, Limits, RuntimeError, Scanner, Value, Vm};
+
+mod common;
+use common::Captured;
+
+#[test]
+fn eval_returns_the_last_value_and_keeps_globals() {
+    let mut vm: Vm = Vm::new();
+    assert_eq!(vm.eval("rakha x ma 2 * 21;").unwrap(), None);
+    assert_eq!(vm.eval("x + 1;").unwrap(), Some(Value::Integer(43)));
+    assert_eq!(vm.eval("x thulo 40;").unwrap(), Some(Value::Boolean(true)));
+    assert_eq!(vm.get_global("x"), Some(&Value::Integer(42)));
+    assert_eq!(vm.get_global("y"), None);
+}
+
+#[test]
+fn globals_set_from_rust_are_visible_to_bish() {
+    let mut vm: Vm = Vm::new();
+    vm.set_global("naam", Value::from("bish"));
+    vm.set_global("umer", Value::Integer(3));
+    assert_eq!(vm.eval("umer ma umer + 1;").unwrap(), None);
+    assert_eq!(vm.get_global("umer"), Some(&Value::Integer(4)));
+    assert_eq!(vm.eval("naam barabar \"bish\";").unwrap(), Some(Value::Boolean(true)));
+}
+
+#[test]
+fn errors_are_returned_and_the_vm_stays_usable() {
+    let mut vm: Vm = Vm::new();
+    assert!(matches!(vm.eval("rakha ma;"), Err(Error::Compile)));
+    match vm.eval("dekhau nabhayeko;") {
+        Err(Error::Runtime(error)) => assert_eq!(error.code, ErrorCode::UndefinedVariable),
+        other => panic!("expected an undefined variable error, got {:?}", other)
+    }
+    assert_eq!(vm.eval("1 + 1;").unwrap(), Some(Value::Integer(2)));
+}
+
+#[test]
+fn integer_overflow_is_an_error() {
+    let mut vm: Vm = Vm::new();
+    vm.set_global("sabbhanda_thulo", Value::Integer(i64::MAX));
+    vm.set_global("sabbhanda_sano", Value::Integer(i64::MIN));
+    for code in ["sabbhanda_thulo + 1;", "sabbhanda_sano - 1;", "sabbhanda_thulo * 2;", "-sabbhanda_sano;", "9223372036854775807 + 1;"] {
+        match vm.eval(code) {
+            Err(Error::Runtime(error)) => assert_eq!(error.code, ErrorCode::IntegerOverflow, "{}", code),
+            other => panic!("{}: expected an overflow error, got {:?}", code, other)
+        }
+    }
+    assert!(matches!(vm.eval("99999999999999999999;"), Err(Error::Compile)));
+    assert_eq!(vm.eval("sabbhanda_thulo - 1 + 1;").unwrap(), Some(Value::Integer(i64::MAX)));
+}
+
+#[test]
+fn equal_strings_and_names_are_interned() {
+    let mut vm: Vm = Vm::new();
+    vm.eval("rakha a ma \"namaste\"; rakha b ma \"namaste\"; rakha x ma 1; x ma x + 1;").unwrap();
+    match (vm.get_global("a"), vm.get_global("b")) {
+        (Some(Value::String(a)), Some(Value::String(b))) => assert!(Rc::ptr_eq(a, b)),
+        other => panic!("expected two strings, got {:?}", other)
+    }
+    // Every use of `x` shares one constant.
+    let names: usize = vm.chunk().const_pool.data.iter().filter(|item| item.data == Value::from("x")).count();
+    assert_eq!(names, 1);
+
+    // Constants compare by address, strings from the host by their text.
+    vm.set_global("host", Value::from("namaste"));
+    assert_eq!(vm.eval("a barabar \"namaste\";").unwrap(), Some(Value::Boolean(true)));
+    assert_eq!(vm.eval("a barabar \"namaskar\";").unwrap(), Some(Value::Boolean(false)));
+    assert_eq!(vm.eval("a barabar host;").unwrap(), Some(Value::Boolean(true)));
+
+    // Code compiled elsewhere resolves its names against this Vm's globals.
+    let mut other: Vm = Vm::new();
+    other.compile("<other>", String::from("rakha c ma x * 10;")).unwrap();
+    let compiled: PathBuf = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("interned.bishc");
+    fs::write(&compiled, bytecode::save(other.chunk()).unwrap()).unwrap();
+    vm.run_file(compiled.to_str().unwrap()).unwrap();
+    assert_eq!(vm.get_global("c"), Some(&Value::Integer(20)));
+    let mut names: Vec<&str> = vm.globals().map(|(name, _)| name).collect();
+    names.sort();
+    assert_eq!(names, ["a", "b", "c", "host", "x"]);
+}
+
+#[test]
+fn run_file_runs_source_and_bytecode() {
+    let dir: PathBuf = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("embed");
+    fs::create_dir_all(&dir).unwrap();
+    let source: PathBuf = dir.join("program.bish");
+    fs::write(&source, "rakha natija ma 6 * 7;").unwrap();
+
+    let mut vm: Vm = Vm::new();
+    vm.run_file(source.to_str().unwrap()).unwrap();
+    assert_eq!(vm.get_global("natija"), Some(&Value::Integer(42)));
+
+    let compiled: PathBuf = dir.join("program.bishc");
+    fs::write(&compiled, bytecode::save(vm.chunk()).unwrap()).unwrap();
+    let mut vm: Vm = Vm::new();
+    vm.run_file(compiled.to_str().unwrap()).unwrap();
+    assert_eq!(vm.get_global("natija"), Some(&Value::Integer(42)));
+
+    match Vm::new().run_file(dir.join("missing.bish").to_str().unwrap()) {
+        Err(Error::Runtime(error)) => assert_eq!(error.code, ErrorCode::UnreadableFile),
+        other => panic!("expected an unreadable file error, got {:?}", other)
+    }
+}
+
+#[test]
+fn chunks_holding_runtime_values_are_not_saved() {
+    let mut chunk: Chunk = Chunk::new();
+    chunk.write_const(Value::Integer(1));
+    chunk.write_const(Value::array(vec![Value::Integer(2)]));
+    match bytecode::save(&chunk) {
+        Err(bytecode::SaveError::UnsupportedConstant(index, type_name)) => assert_eq!((index, type_name.as_str()), (1, "array")),
+        other => panic!("expected the array constant to be refused, got {:?}", other)
+    }
+}
+
+#[test]
+fn programs_too_big_for_u16_operands_do_not_compile() {
+    let many_constants: String = (0..70_000).map(|n| format!("dekhau {};\n", n)).collect();
+    assert!(matches!(Vm::new().compile("<constants>", many_constants), Err(Error::Compile)));
+
+    let long_block: String = format!("rakha x ma galat; yadi x {{ {} }}", "dekhau 1;".repeat(20_000));
+    assert!(matches!(Vm::new().compile("<block>", long_block), Err(Error::Compile)));
+    let long_loop: String = format!("rakha x ma galat; ghumau x {{ {} }}", "dekhau 1;".repeat(20_000));
+    assert!(matches!(Vm::new().compile("<loop>", long_loop), Err(Error::Compile)));
+
+    // Just under the limits still compiles.
+    let fits: String = format!("rakha x ma galat; yadi x {{ {} }}", "dekhau 1;".repeat(16_000));
+    assert!(Vm::new().compile("<fits>", fits).is_ok());
+}
+
+#[test]
+fn scanner_and_parser_can_be_used_on_their_own() {
+    let tokens = Scanner::new(String::from("rakha x ma 1; dekhau x;")).start_scan();
+    assert!(!tokens.is_empty());
+    let program: Vec<Stmt> = compiler::Parser::new(String::from("<test>"), &tokens).parse().unwrap();
+    assert!(matches!(program.as_slice(), [Stmt::VarDecl { .. }, Stmt::Print { .. }]));
+    // Tokens are placed at the column they start at.
+    let tokens = Scanner::new(String::from("rakha nam ma 1;\n\ndekhau   nam;")).start_scan();
+    let places: Vec<(usize, usize)> = tokens.iter().map(|token| (token.line, token.column)).collect();
+    assert_eq!(&places[..8], [(1, 1), (1, 7), (1, 11), (1, 14), (1, 15), (3, 1), (3, 10), (3, 13)]);
+    assert_eq!(compiler::parse("<test>", String::from("dekhau 1;")).map(|program| program.len()), Some(1));
+}
+
+#[test]
+fn formatted_code_parses_back_to_the_same_values() {
+    let source: &str = "dekhau 100000000000000000000.0; dekhau 0.0000001; dekhau 2.5; dekhau 3.0; dekhau 12345678901234567890.5;";
+    let formatted: String = formatter::format(&compiler::parse("<test>", String::from(source)).unwrap());
+    assert!(formatted.contains("dekhau 100000000000000000000.0;\ndekhau 0.0000001;"), "{}", formatted);
+    let reparsed: Vec<Stmt> = compiler::parse("<formatted>", formatted.clone()).expect("formatted code should parse");
+    assert_eq!(formatter::format(&reparsed), formatted);
+
+    let run = |code: &str| {
+        let captured: Captured = Captured::default();
+        let mut vm: Vm = Vm::new();
+        vm.set_output(Box::new(captured.clone()));
+        vm.eval(code).unwrap();
+        let output: String = String::from_utf8_lossy(&captured.0.borrow()).into_owned();
+        output
+    };
+    assert_eq!(run(source), run(&formatted));
+}
+
+fn jod(_vm: &mut Vm, args: &[Value]) -> Result<Value, RuntimeError> {
+    match (&args[0], &args[1]) {
+        (Value::Integer(a), Value::Integer(b)) => Ok(Value::Integer(a + b)),
+        _ => Err(RuntimeError::native("duita int chahinchha"))
+    }
+}
+
+fn sankhya(_vm: &mut Vm, args: &[Value]) -> Result<Value, RuntimeError> {
+    match &args[0] {
+        Value::String(text) => Ok(Value::Integer(text.trim().parse::<i64>()?)),
+        _ => Err(RuntimeError::native("string chahinchha"))
+    }
+}
+
+fn ganti(vm: &mut Vm, _args: &[Value]) -> Result<Value, RuntimeError> {
+    let count: i64 = match vm.get_global("gantiyo") {
+        Some(Value::Integer(count)) => count + 1,
+        _ => 1
+    };
+    vm.set_global("gantiyo", Value::Integer(count));
+    Ok(Value::Integer(count))
+}
+
+#[test]
+fn native_functions_are_called_from_bish() {
+    let mut vm: Vm = Vm::new();
+    vm.register_native("jod", 2, jod);
+    vm.register_native("sankhya", 1, sankhya);
+    vm.register_native("ganti", 0, ganti);
+    assert_eq!(vm.eval("jod(2, jod(3, 4)) * 2;").unwrap(), Some(Value::Integer(18)));
+    assert_eq!(vm.eval("sankhya(\" 42 \") + 1;").unwrap(), Some(Value::Integer(43)));
+    assert_eq!(vm.eval("ganti(); ganti();").unwrap(), Some(Value::Integer(2)));
+    assert_eq!(vm.get_global("gantiyo"), Some(&Value::Integer(2)));
+}
+
+fn suchi(vm: &mut Vm, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(vm.new_array(args.to_vec()))
+}
+
+fn thap(_vm: &mut Vm, args: &[Value]) -> Result<Value, RuntimeError> {
+    match &args[0] {
+        Value::Array(values) => {
+            values.borrow_mut().push(args[1].clone());
+            Ok(Value::Nil)
+        },
+        _ => Err(RuntimeError::native("array chahinchha"))
+    }
+}
+
+#[test]
+fn heap_values_are_shared_and_functions_are_values() {
+    let mut vm: Vm = Vm::new();
+    vm.register_native("jod", 2, jod);
+    vm.register_native("suchi", 3, suchi);
+
+    vm.eval("rakha naam ma \"bish\"; rakha arko ma naam;").unwrap();
+    match (vm.get_global("naam"), vm.get_global("arko")) {
+        (Some(Value::String(a)), Some(Value::String(b))) => assert!(Rc::ptr_eq(a, b)),
+        other => panic!("expected two strings, got {:?}", other)
+    }
+
+    assert_eq!(vm.eval("rakha f ma jod; f(20, 22);").unwrap(), Some(Value::Integer(42)));
+    assert_eq!(vm.eval("f barabar jod;").unwrap(), Some(Value::Boolean(true)));
+    assert_eq!(Value::literal(vm.get_global("f").unwrap()), "<function jod>");
+
+    let captured: Captured = Captured::default();
+    vm.set_output(Box::new(captured.clone()));
+    vm.eval("rakha s ma suchi(1, \"dui\", nil); dekhau s;").unwrap();
+    assert_eq!(vm.get_global("s"), Some(&Value::array(vec![Value::Integer(1), Value::from("dui"), Value::Nil])));
+    assert_eq!(String::from_utf8_lossy(&captured.0.borrow()), "[1, \"dui\", nil]\n");
+}
+
+#[test]
+fn values_fit_in_two_words() {
+    // A tag and one word of payload: numbers inline, everything else a thin
+    // pointer. Growing this slows every push and pop.
+    assert_eq!(std::mem::size_of::<Value>(), 16);
+    assert_eq!(Value::from("bish"), Value::String(Rc::new(String::from("bish"))));
+}
+
+#[test]
+fn cycles_are_collected_once_unreachable() {
+    let mut vm: Vm = Vm::new();
+    vm.register_native("suchi", 3, suchi);
+    vm.register_native("thap", 2, thap);
+    vm.eval("rakha s ma suchi(1, 2, 3); thap(s, s);").unwrap();
+    let cycle = match vm.get_global("s") {
+        Some(Value::Array(values)) => Rc::downgrade(values),
+        other => panic!("expected an array, got {:?}", other)
+    };
+    assert_eq!(vm.collect_garbage(), 0);
+    vm.eval("s ma nil;").unwrap();
+    // Nothing refers to the array but itself, which reference counting alone
+    // never frees.
+    assert!(cycle.upgrade().is_some());
+    assert_eq!(vm.collect_garbage(), 1);
+    assert!(cycle.upgrade().is_none());
+
+    // A value the host holds is a root even though no global refers to it.
+    let held: Value = vm.new_array(Vec::new());
+    if let Value::Array(values) = &held {
+        values.borrow_mut().push(held.clone());
+    }
+    assert_eq!(vm.collect_garbage(), 0);
+    assert!(matches!(&held, Value::Array(values) if values.borrow().len() == 1));
+}
+
+#[test]
+fn separate_cycles_compare_without_overflowing() {
+    let mut vm: Vm = Vm::new();
+    vm.register_native("suchi", 3, suchi);
+    vm.register_native("thap", 2, thap);
+    vm.eval("rakha a ma suchi(1, 2, 3); thap(a, a); rakha b ma suchi(1, 2, 3); thap(b, b);").unwrap();
+    assert_eq!(vm.eval("a barabar b;").unwrap(), Some(Value::Boolean(true)));
+
+    // Same shape, different data inside the cycle.
+    vm.eval("rakha c ma suchi(1, 2, 4); thap(c, c);").unwrap();
+    assert_eq!(vm.eval("a barabar c;").unwrap(), Some(Value::Boolean(false)));
+
+    // Cycles that pass through each other.
+    vm.eval("rakha d ma suchi(1, 2, 3); rakha e ma suchi(1, 2, 3); thap(d, e); thap(e, d);").unwrap();
+    assert_eq!(vm.eval("d barabar e;").unwrap(), Some(Value::Boolean(true)));
+    assert_eq!(vm.get_global("a"), vm.get_global("d"));
+}
+
+#[test]
+fn stress_mode_collects_on_every_allocation() {
+    let mut vm: Vm = Vm::new();
+    vm.register_native("suchi", 3, suchi);
+    vm.register_native("thap", 2, thap);
+    vm.gc.stress = true;
+    vm.eval("rakha i ma 0; ghumau i sano 50 { rakha s ma suchi(i, suchi(1, 2, 3), nil); thap(s, s); i ma i + 1; }").unwrap();
+    assert_eq!(vm.gc.collections, 100);
+    assert_eq!(vm.eval("s;").map(|value| value.map(|value| Value::literal(&value).starts_with("[49, [1, 2, 3], nil, "))).unwrap(), Some(true));
+    assert!(vm.gc.len() <= 4, "{} objects still tracked", vm.gc.len());
+}
+
+fn bhitra(vm: &mut Vm, args: &[Value]) -> Result<Value, RuntimeError> {
+    let source: String = match &args[0] {
+        Value::String(source) => source.to_string(),
+        _ => return Err(RuntimeError::native("code string ma chahinchha"))
+    };
+    match vm.eval(&source) {
+        Ok(value) => Ok(value.unwrap_or(Value::Nil)),
+        Err(Error::Runtime(error)) => Err(error),
+        Err(Error::Compile) => Err(RuntimeError::native("code compile bhayena"))
+    }
+}
+
+#[test]
+fn natives_can_run_bish_code_and_the_caller_carries_on() {
+    let mut vm: Vm = Vm::new();
+    vm.register_native("bhitra", 1, bhitra);
+    let captured: Captured = Captured::default();
+    vm.set_output(Box::new(captured.clone()));
+    // bish strings have no escapes, so the code a native runs comes from
+    // globals.
+    vm.set_global("kod", Value::from("rakha y ma 2; dekhau \"inner\"; 2 + 3;"));
+    let program: &str = "rakha x ma 1; rakha a ma bhitra(kod); dekhau \"after\"; a + x + y;";
+    assert_eq!(vm.eval(program).unwrap(), Some(Value::Integer(8)));
+    assert_eq!(vm.get_global("a"), Some(&Value::Integer(5)));
+    assert_eq!(String::from_utf8_lossy(&captured.0.borrow()), "inner\nafter\n");
+
+    // Each level of nesting is a native call in progress.
+    vm.set_global("ek", Value::from("1;"));
+    vm.set_global("dui", Value::from("bhitra(ek);"));
+    vm.set_global("tin", Value::from("bhitra(dui);"));
+    vm.limits.call_depth = Some(2);
+    assert_eq!(vm.eval("bhitra(dui);").unwrap(), Some(Value::Integer(1)));
+    match vm.eval("bhitra(tin);") {
+        Err(Error::Runtime(error)) => assert_eq!(error.code, ErrorCode::LimitExceeded),
+        other => panic!("expected the call depth limit, got {:?}", other)
+    }
+    assert_eq!(vm.eval("x + 1;").unwrap(), Some(Value::Integer(2)));
+}
+
+#[test]
+fn native_function_errors_become_bish_errors() {
+    let mut vm: Vm = Vm::new();
+    vm.register_native("jod", 2, jod);
+    vm.register_native("sankhya", 1, sankhya);
+    let error = |vm: &mut Vm, code: &str| match vm.eval(code) {
+        Err(Error::Runtime(error)) => error,
+        other => panic!("{} should fail at runtime, got {:?}", code, other)
+    };
+
+    let failed: RuntimeError = error(&mut vm, "jod(1, \"dui\");");
+    assert_eq!(failed.code, ErrorCode::NativeFunctionError);
+    assert_eq!(failed.message, "'jod': duita int chahinchha");
+
+    let failed: RuntimeError = error(&mut vm, "sankhya(\"ek\");");
+    assert_eq!(failed.code, ErrorCode::NativeFunctionError);
+    assert!(failed.message.starts_with("'sankhya': invalid digit"), "{}", failed.message);
+
+    assert_eq!(error(&mut vm, "jod(1);").code, ErrorCode::WrongArgumentCount);
+    let failed: RuntimeError = error(&mut vm, "jood(1, 2);");
+    assert_eq!(failed.code, ErrorCode::UndefinedFunction);
+    assert!(failed.message.contains("jod"), "{}", failed.message);
+}
+
+#[test]
+fn limits_stop_programs_that_use_too_much() {
+    let limit = |vm: &mut Vm, code: &str| match vm.eval(code) {
+        Err(Error::Runtime(error)) if error.code == ErrorCode::LimitExceeded => error.message,
+        other => panic!("{} should go over a limit, got {:?}", code, other)
+    };
+
+    let mut vm: Vm = Vm::new();
+    vm.limits.instructions = Some(1000);
+    assert!(limit(&mut vm, "rakha i ma 0; ghumau sahi { i ma i + 1; }").contains("'instructions'"));
+    assert!(matches!(vm.get_global("i"), Some(Value::Integer(count)) if *count > 0));
+
+    let mut vm: Vm = Vm::new();
+    vm.eval("rakha x ma 1 + 2;").unwrap();
+    let executed: u64 = vm.executed();
+    vm.limits.instructions = Some(executed);
+    assert!(vm.eval("rakha x ma 1 + 2;").is_ok());
+    vm.limits.instructions = Some(executed - 1);
+    assert!(limit(&mut vm, "rakha x ma 1 + 2;").contains("'instructions'"));
+
+    // Each open parenthesis keeps one more operand waiting on the stack.
+    let deep: String = format!("rakha x ma 1; {}x{};", "x + (".repeat(300), ")".repeat(300));
+    let mut vm: Vm = Vm::new();
+    assert!(limit(&mut vm, &deep).contains("'stack'"));
+    vm.limits.stack = None;
+    assert_eq!(vm.eval(&deep).unwrap(), Some(Value::Integer(301)));
+    // Results of earlier statements do not pile up.
+    vm.limits.stack = Some(8);
+    assert_eq!(vm.eval(&"1;".repeat(300)).unwrap(), Some(Value::Integer(1)));
+
+    let mut vm: Vm = Vm::new();
+    vm.limits.heap_bytes = Some(64);
+    vm.eval("rakha naam ma \"bish\";").unwrap();
+    assert!(limit(&mut vm, &format!("rakha lamo ma \"{}\";", "a".repeat(100))).contains("'heap_bytes'"));
+    // A program that never ends is stopped by the periodic check.
+    vm.register_native("suchi", 3, suchi);
+    vm.register_native("thap", 2, thap);
+    vm.limits.heap_bytes = Some(4096);
+    assert!(limit(&mut vm, "rakha s ma suchi(1, 2, 3); ghumau sahi { thap(s, 1); }").contains("'heap_bytes'"));
+
+    let mut vm: Vm = Vm::new();
+    vm.register_native("jod", 2, jod);
+    vm.limits.call_depth = Some(0);
+    assert!(limit(&mut vm, "jod(1, 2);").contains("'call_depth'"));
+
+    let mut limits: Limits = Limits::unlimited();
+    assert!(limits.apply("instructions=10") && limits.apply("stack=none"));
+    assert_eq!((limits.instructions, limits.stack), (Some(10), None));
+    assert!(!limits.apply("instructions") && !limits.apply("samay=5") && !limits.apply("stack=-1"));
+}
+
+#[test]
+fn running_programs_can_be_cancelled() {
+    let cancelled = |vm: &mut Vm| match vm.eval("ghumau sahi { }") {
+        Err(Error::Runtime(error)) => assert_eq!(error.code, ErrorCode::Cancelled),
+        other => panic!("the loop should have been cancelled, got {:?}", other)
+    };
+
+    let mut vm: Vm = Vm::new();
+    let handle: InterruptHandle = vm.interrupt_handle();
+    let interrupter = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        handle.interrupt();
+    });
+    cancelled(&mut vm);
+    interrupter.join().unwrap();
+    // The interrupt only stops the run it caught.
+    assert_eq!(vm.eval("1 + 1;").unwrap(), Some(Value::Integer(2)));
+    // Nor does one sent while nothing runs stop the next run.
+    vm.interrupt_handle().interrupt();
+    vm.eval("rakha i ma 0; ghumau i sano 5000 { i ma i + 1; }").unwrap();
+    assert_eq!(vm.get_global("i"), Some(&Value::Integer(5000)));
+
+    vm.limits.timeout = Some(Duration::from_millis(50));
+    cancelled(&mut vm);
+    assert_eq!(vm.eval("2 + 2;").unwrap(), Some(Value::Integer(4)));
+}
+
+#[test]
+fn builtins_need_their_capability_granted() {
+    let dir: PathBuf = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("capabilities");
+    fs::create_dir_all(&dir).unwrap();
+    let notes: String = dir.join("notes.txt").to_str().unwrap().replace('\\', "/");
+
+    let mut vm: Vm = Vm::new();
+    vm.register_native("jod", 2, jod);
+    let write: String = format!("lekh(\"{}\", \"namaste\");", notes);
+    match vm.eval(&write) {
+        Err(Error::Runtime(error)) => {
+            assert_eq!(error.code, ErrorCode::CapabilityDenied);
+            assert!(error.message.contains("'fs'"), "{}", error.message);
+        },
+        other => panic!("lekh should need 'fs', got {:?}", other)
+    }
+    // Natives the host registers itself are not affected.
+    assert_eq!(vm.eval("jod(1, 2);").unwrap(), Some(Value::Integer(3)));
+
+    vm.capabilities.grant(Capability::Fs);
+    vm.eval(&write).unwrap();
+    assert_eq!(vm.eval(&format!("padh(\"{}\");", notes)).unwrap(), Some(Value::from("namaste")));
+    assert!(matches!(vm.eval("samay();"), Err(Error::Runtime(error)) if error.code == ErrorCode::CapabilityDenied));
+
+    vm.capabilities = Capabilities::all();
+    assert!(matches!(vm.eval("samay();").unwrap(), Some(Value::Double(seconds)) if seconds > 0.0));
+    assert!(matches!(vm.eval("jathabhavi();").unwrap(), Some(Value::Double(number)) if (0.0..1.0).contains(&number)));
+    vm.capabilities.revoke(Capability::Fs);
+    assert!(vm.eval(&write).is_err());
+}
+
+struct Broken;
+
+impl Write for Broken {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::BrokenPipe, "pipe banda bhayo"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn dekhau_writes_to_the_output_set_by_the_host() {
+    let captured: Captured = Captured::default();
+    let mut vm: Vm = Vm::new();
+    vm.set_output(Box::new(captured.clone()));
+    vm.eval("dekhau \"namaste\"; dekhau 1.5 * 2; dekhau sahi; dekhau nil;").unwrap();
+    assert_eq!(String::from_utf8_lossy(&captured.0.borrow()), "namaste\n3\nsahi\nnil\n");
+
+    vm.set_output(Box::new(Broken));
+    match vm.eval("dekhau 1;") {
+        Err(Error::Runtime(error)) => assert_eq!(error.code, ErrorCode::UnwritableFile),
+        other => panic!("expected a write error, got {:?}", other)
+    }
+}
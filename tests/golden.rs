@@ -0,0 +1,134 @@
+// MIT License
+//
+// Copyright (c) 2023 Ramesh Poudel
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+/*
+* Runs every .bish program in tests/golden through the bish binary and compares
+* its exit code, stdout and stderr with the .expected file next to it. If a
+* .args file sits next to the program too, the words in it are passed to bish
+* before the program's path, e.g. `disasm` or `run --trace`.
+*
+* golden_programs_in_process runs the same programs through bish::Vm with
+* 'dekhau' captured in memory, and checks the exit code and stdout parts of
+* the same files. Diagnostics go straight to stderr, so those are left to
+* the binary.
+*
+* After an intended change in output, regenerate the expected files with
+*
+*   BISH_BLESS=1 cargo test --test golden
+*
+* and review the diff before committing.
+*/
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use bish::{Error, Vm};
+
+mod common;
+use common::Captured;
+
+fn run_bish(manifest_dir: &Path, program: &Path) -> String {
+    let relative: &Path = program.strip_prefix(manifest_dir).unwrap();
+    let args: String = fs::read_to_string(program.with_extension("args")).unwrap_or_default();
+    let output: Output = Command::new(env!("CARGO_BIN_EXE_bish"))
+        .args(args.split_whitespace())
+        .arg(relative)
+        .current_dir(manifest_dir)
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("bish binary should start");
+    format!(
+        "exit: {}\n--- stdout\n{}--- stderr\n{}",
+        output.status.code().map_or(String::from("signal"), |code| code.to_string()),
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )
+}
+
+fn run_in_process(program: &Path) -> String {
+    let captured: Captured = Captured::default();
+    let mut vm: Vm = Vm::new();
+    vm.set_output(Box::new(captured.clone()));
+    let result: Result<(), Error> = vm.run_file(program.to_str().unwrap());
+    let mut stdout: String = String::from_utf8_lossy(&captured.0.borrow()).into_owned();
+    // What the bish command prints to stdout on a compile error.
+    if let Err(Error::Compile) = result {
+        stdout.push_str("compile error. terminated.\n");
+    }
+    format!("exit: {}\n--- stdout\n{}", result.map_or_else(|error| error.exit_code(), |()| 0), stdout)
+}
+
+fn list_programs(manifest_dir: &Path) -> Vec<PathBuf> {
+    let mut programs: Vec<PathBuf> = fs::read_dir(manifest_dir.join("tests/golden"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "bish"))
+        .collect();
+    programs.sort();
+    assert!(!programs.is_empty(), "no golden programs found");
+    programs
+}
+
+#[test]
+fn golden_programs() {
+    let manifest_dir: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let bless: bool = std::env::var_os("BISH_BLESS").is_some();
+
+    let programs: Vec<PathBuf> = list_programs(&manifest_dir);
+
+    let mut failures: Vec<String> = Vec::new();
+    for program in &programs {
+        let actual: String = run_bish(&manifest_dir, program);
+        let expected_path: PathBuf = program.with_extension("expected");
+        if bless {
+            fs::write(&expected_path, &actual).unwrap();
+            continue;
+        }
+        match fs::read_to_string(&expected_path) {
+            Ok(expected) if expected == actual => (),
+            Ok(expected) => failures.push(format!(
+                "{}\n===== expected\n{}===== actual\n{}",
+                program.display(), expected, actual
+            )),
+            Err(_) => failures.push(format!("{}: missing {}", program.display(), expected_path.display()))
+        }
+    }
+
+    assert!(failures.is_empty(), "{} golden test(s) failed:\n\n{}", failures.len(), failures.join("\n"));
+}
+
+#[test]
+fn golden_programs_in_process() {
+    let manifest_dir: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let mut failures: Vec<String> = Vec::new();
+    // Programs with a .args file test a command-line option.
+    for program in list_programs(&manifest_dir).iter().filter(|program| !program.with_extension("args").exists()) {
+        let expected: String = fs::read_to_string(program.with_extension("expected")).unwrap_or_default();
+        let expected: &str = expected.split("--- stderr\n").next().unwrap();
+        let actual: String = run_in_process(program);
+        if actual != expected {
+            failures.push(format!("{}\n===== expected\n{}===== actual\n{}", program.display(), expected, actual));
+        }
+    }
+    assert!(failures.is_empty(), "{} golden test(s) failed in process:\n\n{}", failures.len(), failures.join("\n"));
+}
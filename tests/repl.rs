@@ -0,0 +1,108 @@
+// MIT License
+//
+// Copyright (c) 2023 Ramesh Poudel
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+/*
+* Drives `bish` with no arguments, which starts the REPL, through a pipe.
+* Prompts are only printed to a terminal, so stdout holds just the output.
+*/
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn repl(input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_bish"))
+        .env("NO_COLOR", "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("bish binary should start");
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn globals_persist_and_expressions_are_echoed() {
+    let output: Output = repl("rakha x ma 5;\nx + 1\n\"namaste\"\nx ma x * 2;\ndekhau x;\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "6\n\"namaste\"\n10\n");
+    assert!(output.stderr.is_empty(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn unbalanced_braces_continue_the_entry() {
+    let output: Output = repl("rakha i ma 0;\nghumau i sano 2 {\n    dekhau i;\n    i ma i + 1;\n}\ndekhau (1 +\n2);\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "0\n1\n3\n");
+}
+
+#[test]
+fn errors_do_not_end_the_session() {
+    let output: Output = repl("dekhau y;\nrakha y ma 1;\ndekhau y galat;\ndekhau y;\n");
+    let stderr: String = String::from_utf8_lossy(&output.stderr).into_owned();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n");
+    assert!(stderr.contains("B0018"), "{}", stderr);
+    assert!(stderr.contains("B0020"), "{}", stderr);
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn meta_commands() {
+    let output: Output = repl(":globals\nrakha x ma 5;\nrakha naam ma \"bish\";\n:globals\n:type x thulo 2\n:dis x + 1\n:reset\n:globals\n:load tests/golden/loop.bish\n:globals\n:time 1 + 1\n");
+    let stdout: String = String::from_utf8_lossy(&output.stdout).into_owned();
+    let expected_start: &str = "\
+naam: string = \"bish\"
+x: int = 5
+bool
+== <repl> ==
+-- constants --
+    0  string  \"x\"
+    1  int     1
+-- code --
+0000    1 OP_LOAD_GLOBAL       0 \"x\"
+0003    | OP_CONST             1 1
+0006    | OP_ADD
+0
+1
+2
+i: int = 3
+2
+samay: ";
+    assert!(stdout.starts_with(expected_start), "{}", stdout);
+    assert!(output.stderr.is_empty(), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn unknown_meta_command() {
+    let output: Output = repl(":nope\n:type\n");
+    let stderr: String = String::from_utf8_lossy(&output.stderr).into_owned();
+    assert!(stderr.contains("':nope' bhanne command chhaina"), "{}", stderr);
+    assert!(stderr.contains("':type' pachhi"), "{}", stderr);
+}
+
+#[test]
+fn type_is_worked_out_without_running() {
+    let output: Output = repl("rakha x ma 5;\n:type x * 1.5\n:type (x + 1) thulo 2\n:type samay\n:type samay()\n:type rakha y ma 1;\n:type \"a\" thulo 1\n:type \"a\" barabar 1\n:type \"a\" barabar \"b\"\n:globals\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "float\nbool\nfunction\nbool\nx: int = 5\n");
+    let stderr: String = String::from_utf8_lossy(&output.stderr).into_owned();
+    assert!(stderr.contains("nachalai thaha hudaina"), "{}", stderr);
+    assert!(stderr.contains("euta expression chahinchha"), "{}", stderr);
+}